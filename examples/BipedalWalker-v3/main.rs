@@ -123,19 +123,24 @@ fn train(standard_scaler: StandardScaler) {
     let neat = Runtime::new(
         &format!("examples/{}/config.toml", ENV),
         Box::new(fitness_function),
-    );
+    )
+    .expect("could not load config");
 
     let now = Instant::now();
 
     info!(target: "app::parameters", "starting training: {:#?}", neat.parameters);
 
-    if let Some(winner_map) = neat.initilize().find(|elites_map| {
+    let mut search = neat.initilize();
+
+    while let Some(report) = search.next() {
+        let elites_map = search.archive();
+
         println!(
             "map cells: {}, map capacity: {}",
-            elites_map.len(),
+            report.occupied_cells,
             elites_map.capacity()
         );
-        info!(target: "training::top-fitness", fitness = ?elites_map.top_individual().fitness);
+        info!(target: "training::top-fitness", fitness = ?report.best_fitness);
         for top in &elites_map.sorted_individuals()[0..5] {
             run(
                 &other_standard_scaler,
@@ -146,8 +151,13 @@ fn train(standard_scaler: StandardScaler) {
                 false,
             );
         }
-        elites_map.top_individual().fitness > REQUIRED_FITNESS
-    }) {
+
+        if report.best_fitness <= REQUIRED_FITNESS {
+            continue;
+        }
+
+        let winner_map = elites_map;
+
         fs::write(
             format!("examples/{}/{}_winner.json", ENV, timestamp()),
             serde_json::to_string(&winner_map.top_individual()).unwrap(),
@@ -180,6 +190,8 @@ fn train(standard_scaler: StandardScaler) {
             now.elapsed().as_secs(),
             winner_map.top_individual()
         );
+
+        break;
     }
 }
 
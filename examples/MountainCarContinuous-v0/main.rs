@@ -138,13 +138,18 @@ fn train(standard_scaler: (Array1<f64>, Array1<f64>)) {
     let runtime = Runtime::new(
         &format!("examples/{}/config.toml", ENV),
         Box::new(fitness_function),
-    );
+    )
+    .expect("could not load config");
 
     let now = Instant::now();
 
     info!(target: "app::parameters", "starting training...\nRUNS:{:#?}\nVALIDATION_RUNS:{:#?}\nSTEPS: {:#?}\nREQUIRED_FITNESS:{:#?}\nPARAMETERS: {:#?}", RUNS, VALIDATION_RUNS, STEPS, REQUIRED_FITNESS, runtime.parameters);
 
-    if let Some((_, winner_map)) = runtime.initilize().enumerate().find(|(batch, elites_map)| {
+    let mut search = runtime.initilize();
+
+    while let Some(report) = search.next() {
+        let elites_map = search.archive();
+
         run(
             &other_standard_scaler,
             &elites_map.top_individual(),
@@ -153,9 +158,17 @@ fn train(standard_scaler: (Array1<f64>, Array1<f64>)) {
             false,
             false,
         );
-        dbg!("batch {} status: {:?}", batch, elites_map.top_individual());
-        elites_map.top_individual().fitness > REQUIRED_FITNESS
-    }) {
+        dbg!(
+            "batch {} status: {:?}",
+            report.batch,
+            elites_map.top_individual()
+        );
+
+        if report.best_fitness <= REQUIRED_FITNESS {
+            continue;
+        }
+
+        let winner_map = elites_map;
         let time_stamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
@@ -203,6 +216,8 @@ fn train(standard_scaler: (Array1<f64>, Array1<f64>)) {
             secs as f64 / 1000.0,
             winner_map.top_individual()
         );
+
+        break;
     }
 }
 
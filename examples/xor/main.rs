@@ -6,26 +6,18 @@ use std::{ops::Deref, time::Instant};
 use map_elites::{Individual, Runtime};
 
 fn main() {
-    let fitness_function = |individual: &Individual| -> (f64, Vec<f64>) {
-        let result_0;
-        let result_1;
-        let result_2;
-        let result_3;
-
-        /* match LoopingFabricator::fabricate(individual) {
-        Ok(mut evaluator) => { */
-        match FeedForwardMatrixFabricator::fabricate(individual.deref()) {
-            Ok(evaluator) => {
-                result_0 = evaluator.evaluate(array![1.0, 1.0, 0.0]);
-                result_1 = evaluator.evaluate(array![1.0, 1.0, 1.0]);
-                result_2 = evaluator.evaluate(array![1.0, 0.0, 1.0]);
-                result_3 = evaluator.evaluate(array![1.0, 0.0, 0.0]);
-            }
-            Err(e) => {
-                println!("error fabricating individual: {:?} {:?}", individual, e);
-                panic!("")
-            }
-        }
+    let fitness_function = |individual: &Individual| -> Result<
+        (f64, Vec<f64>),
+        Box<dyn std::error::Error + Send + Sync>,
+    > {
+        // let evaluator = LoopingFabricator::fabricate(individual)
+        let evaluator = FeedForwardMatrixFabricator::fabricate(individual.deref())
+            .map_err(|e| format!("error fabricating individual: {:?}", e))?;
+
+        let result_0 = evaluator.evaluate(array![1.0, 1.0, 0.0]);
+        let result_1 = evaluator.evaluate(array![1.0, 1.0, 1.0]);
+        let result_2 = evaluator.evaluate(array![1.0, 0.0, 1.0]);
+        let result_3 = evaluator.evaluate(array![1.0, 0.0, 0.0]);
 
         // calculate fitness
 
@@ -36,16 +28,17 @@ fn main() {
                 + (0.0 - result_3[0]).abs()))
         .powi(2);
 
-        (
+        Ok((
             fitness,
             vec![
                 individual.feed_forward.len() as f64,
                 individual.hidden.len() as f64,
             ],
-        )
+        ))
     };
 
-    let runtime = Runtime::new("examples/xor/config.toml", Box::new(fitness_function));
+    let runtime = Runtime::new_fallible("examples/xor/config.toml", Box::new(fitness_function))
+        .expect("could not load config");
 
     let mut millis_elapsed_in_run = Vec::new();
     let mut connections_in_winner_in_run = Vec::new();
@@ -55,17 +48,16 @@ fn main() {
     for i in 0..100 {
         let now = Instant::now();
 
-        if let Some((generations, winner_map)) =
-            runtime
-                .initilize()
-                .enumerate()
-                .find(|(iteration, elites_map)| {
-                    dbg!(iteration);
-                    dbg!(elites_map.top_individual().fitness);
-
-                    elites_map.top_individual().fitness > 15.9
-                })
-        {
+        let mut search = runtime.initilize();
+
+        if let Some(report) = search.find(|report| {
+            dbg!(report.batch);
+            dbg!(report.best_fitness);
+
+            report.best_fitness > 15.9
+        }) {
+            let generations = report.batch;
+            let winner_map = search.archive();
             millis_elapsed_in_run.push(now.elapsed().as_millis() as f64);
             connections_in_winner_in_run.push(winner_map.top_individual().feed_forward.len());
             nodes_in_winner_in_run.push(winner_map.top_individual().nodes().count());
@@ -0,0 +1,109 @@
+use rand::Rng;
+use serde::Serialize;
+
+use crate::{error::Error, individual::Individual, selection::Selector};
+
+/// Storage of evaluated individuals the runtime selects parents from and places offspring into.
+pub trait Archive: Clone + Serialize {
+    /// Stores the individual if it qualifies, based on its fitness and behavior.
+    fn place_individual(&mut self, individual: Individual) -> Insertion;
+
+    /// Like `place_individual` but reports individuals the archive can not handle, e.g. by their descriptor.
+    fn try_place_individual(&mut self, individual: Individual) -> Result<Insertion, Error> {
+        Ok(self.place_individual(individual))
+    }
+
+    /// Whether an individual with `behavior` only gets stored by beating the fitness of a stored one,
+    /// archives that can not tell claim it does not.
+    fn competes_on_fitness(&self, _behavior: &[f64]) -> bool {
+        false
+    }
+
+    /// Picks a parent for the next offspring.
+    fn get_random_individual(&self, rng: &mut impl Rng) -> Individual;
+
+    /// Picks parents for a whole batch, archives should prepare their selection only once.
+    fn get_random_individuals(&self, count: usize, rng: &mut impl Rng) -> Vec<Individual> {
+        (0..count)
+            .map(|_| self.get_random_individual(rng))
+            .collect()
+    }
+
+    /// Picks parents by a specific selector, archives without the notion of cells fall back to their own selection.
+    fn get_random_individuals_with(
+        &self,
+        _selector: &impl Selector,
+        count: usize,
+        rng: &mut impl Rng,
+    ) -> Vec<Individual> {
+        self.get_random_individuals(count, rng)
+    }
+
+    /// Picks a mate from the neighborhood of the stored parent of `individual`,
+    /// archives without a notion of neighborhood do not provide one.
+    fn get_random_neighbor(
+        &self,
+        _individual: &Individual,
+        _rng: &mut impl Rng,
+    ) -> Option<Individual> {
+        None
+    }
+
+    /// All stored individuals in no particular order.
+    fn individuals(&self) -> Box<dyn Iterator<Item = &Individual> + '_>;
+
+    /// Number of occupied cells.
+    fn len(&self) -> usize;
+
+    /// Number of cells available.
+    fn capacity(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn top_individual(&self) -> Individual {
+        self.individuals()
+            .max_by(|a, b| {
+                a.fitness
+                    .partial_cmp(&b.fitness)
+                    .expect("could not compare floats")
+            })
+            .cloned()
+            .expect("archive did not held any individual")
+    }
+}
+
+/// What became of an individual handed to `place_individual`.
+///
+/// Cells are identified by their index at the time of insertion.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Insertion {
+    /// occupies a previously empty cell
+    NewCell { cell: Vec<usize> },
+    /// was added to a cell that had capacity left
+    Joined { cell: Vec<usize> },
+    /// took the place of a stored individual, `fitness_delta` is relative to the replaced one
+    Replaced {
+        cell: Vec<usize>,
+        fitness_delta: f64,
+    },
+    /// was not stored, `cell` is `None` when the behavior was rejected before binning
+    Discarded { cell: Option<Vec<usize>> },
+}
+
+impl Insertion {
+    /// Whether the individual got into the archive.
+    pub fn is_success(&self) -> bool {
+        !matches!(self, Insertion::Discarded { .. })
+    }
+
+    pub fn cell(&self) -> Option<&[usize]> {
+        match self {
+            Insertion::NewCell { cell }
+            | Insertion::Joined { cell }
+            | Insertion::Replaced { cell, .. } => Some(cell),
+            Insertion::Discarded { cell } => cell.as_deref(),
+        }
+    }
+}
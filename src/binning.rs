@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+/// How a feature range is divided into chunks.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Binning {
+    /// equally sized chunks
+    #[default]
+    Linear,
+    /// equally sized chunks over `ln(1 + value - min)`, i.e. finer chunks towards `min`
+    Logarithmic,
+    /// explicit chunk boundaries, the first and last edge span the feature range
+    Edges(Vec<f64>),
+}
+
+impl Binning {
+    /// Index of the chunk `value` falls into, `value` has to be within `[min, max]`.
+    pub fn chunk(&self, value: f64, min: f64, max: f64, resolution: usize) -> usize {
+        match self {
+            // the epsilon vanishes for wide ranges, so `max` itself is put into the last chunk
+            Binning::Linear => (((value - min) / (max - min + 1E-15) * resolution as f64).floor()
+                as usize)
+                .min(resolution - 1),
+            Binning::Logarithmic => (((value - min).ln_1p() / ((max - min).ln_1p() + 1E-15)
+                * resolution as f64)
+                .floor() as usize)
+                .min(resolution - 1),
+            // the last edge closes the last chunk
+            Binning::Edges(edges) => {
+                edges[1..edges.len() - 1].partition_point(|&edge| edge <= value)
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use set_genome::Parameters as GenomeParameters;
+
+use crate::{
+    calibration::Calibration,
+    checkpoint::Checkpoint,
+    elites_map::{CellCapacity, OutOfRange, SlidingBoundaries},
+    emitter::{Bandit, Emitter},
+    error::Error,
+    parameters::{
+        Crossover, EvaluationTimeout, FeatureRange, MapElitesParameters, Parameters, Tessellation,
+    },
+    runtime::{FallibleFitnessFunction, FitnessFunction, Runtime},
+    selection::Selection,
+    storage::StorageBackend,
+};
+
+/// Configures a `Runtime` without a config file, every setting of the config file has its own method.
+pub struct RuntimeBuilder {
+    parameters: Parameters,
+    fitness_function: FallibleFitnessFunction,
+}
+
+impl RuntimeBuilder {
+    /// Starts from default parameters.
+    pub fn new(fitness_function: FitnessFunction) -> Self {
+        Self::new_fallible(Box::new(move |individual| Ok(fitness_function(individual))))
+    }
+
+    /// Starts from default parameters, individuals whose evaluation fails are dropped.
+    pub fn new_fallible(fitness_function: FallibleFitnessFunction) -> Self {
+        Self {
+            parameters: Parameters::default(),
+            fitness_function,
+        }
+    }
+
+    /// Starts from the parameters of a config file.
+    pub fn from_path(path: &str, fitness_function: FitnessFunction) -> Result<Self, Error> {
+        Self::new(fitness_function).load(path)
+    }
+
+    /// Replaces all parameters by those of a config file.
+    pub fn load(mut self, path: &str) -> Result<Self, Error> {
+        self.parameters = Parameters::new(path)?;
+        Ok(self)
+    }
+
+    /// Validates the parameters set so far.
+    pub fn build(self) -> Result<Runtime, Error> {
+        self.parameters.map_elites.validate()?;
+
+        Ok(Runtime::with_parameters(
+            self.parameters,
+            self.fitness_function,
+        ))
+    }
+
+    pub fn map_elites(mut self, map_elites: MapElitesParameters) -> Self {
+        self.parameters.map_elites = map_elites;
+        self
+    }
+
+    pub fn genome(mut self, genome: GenomeParameters) -> Self {
+        self.parameters.genome = genome;
+        self
+    }
+
+    pub fn map_resolution(mut self, map_resolution: usize) -> Self {
+        self.parameters.map_elites.map_resolution = map_resolution;
+        self
+    }
+
+    pub fn feature_ranges(mut self, feature_ranges: Vec<FeatureRange>) -> Self {
+        self.parameters.map_elites.feature_ranges = feature_ranges;
+        self
+    }
+
+    pub fn initial_runs(mut self, initial_runs: usize) -> Self {
+        self.parameters.map_elites.initial_runs = initial_runs;
+        self
+    }
+
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.parameters.map_elites.batch_size = batch_size;
+        self
+    }
+
+    pub fn tessellation(mut self, tessellation: Tessellation) -> Self {
+        self.parameters.map_elites.tessellation = tessellation;
+        self
+    }
+
+    pub fn out_of_range(mut self, out_of_range: OutOfRange) -> Self {
+        self.parameters.map_elites.out_of_range = out_of_range;
+        self
+    }
+
+    pub fn calibration(mut self, calibration: Calibration) -> Self {
+        self.parameters.map_elites.calibration = Some(calibration);
+        self
+    }
+
+    pub fn sliding_boundaries(mut self, sliding_boundaries: SlidingBoundaries) -> Self {
+        self.parameters.map_elites.sliding_boundaries = Some(sliding_boundaries);
+        self
+    }
+
+    pub fn cell_capacity(mut self, cell_capacity: CellCapacity) -> Self {
+        self.parameters.map_elites.cell_capacity = cell_capacity;
+        self
+    }
+
+    pub fn storage(mut self, storage: StorageBackend) -> Self {
+        self.parameters.map_elites.storage = storage;
+        self
+    }
+
+    pub fn selection(mut self, selection: Selection) -> Self {
+        self.parameters.map_elites.selection = selection;
+        self
+    }
+
+    pub fn crossover(mut self, crossover: Crossover) -> Self {
+        self.parameters.map_elites.crossover = crossover;
+        self
+    }
+
+    pub fn emitters(mut self, emitters: Vec<Emitter>) -> Self {
+        self.parameters.map_elites.emitters = emitters;
+        self
+    }
+
+    pub fn bandit(mut self, bandit: Bandit) -> Self {
+        self.parameters.map_elites.bandit = bandit;
+        self
+    }
+
+    pub fn catch_panics(mut self, catch_panics: bool) -> Self {
+        self.parameters.map_elites.catch_panics = catch_panics;
+        self
+    }
+
+    pub fn evaluation_timeout(mut self, evaluation_timeout: EvaluationTimeout) -> Self {
+        self.parameters.map_elites.evaluation_timeout = Some(evaluation_timeout);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.parameters.map_elites.seed = Some(seed);
+        self
+    }
+
+    pub fn checkpoint(mut self, checkpoint: Checkpoint) -> Self {
+        self.parameters.map_elites.checkpoint = Some(checkpoint);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RuntimeBuilder;
+    use crate::{
+        emitter::Bandit,
+        error::Error,
+        parameters::{EvaluationTimeout, FeatureRange},
+        selection::Selection,
+        storage::StorageBackend,
+    };
+
+    #[test]
+    fn build_without_config_file() {
+        let runtime = RuntimeBuilder::new(Box::new(|_| (0.0, vec![0.0])))
+            .map_resolution(10)
+            .feature_ranges(vec![FeatureRange::from((-1.0, 1.0))])
+            .initial_runs(5)
+            .batch_size(3)
+            .storage(StorageBackend::Dense)
+            .selection(Selection::Uniform)
+            .build()
+            .unwrap();
+
+        let map_elites = &runtime.parameters.map_elites;
+
+        assert_eq!(map_elites.map_resolution, 10);
+        assert_eq!(map_elites.bounds(), vec![(-1.0, 1.0)]);
+        assert_eq!(map_elites.initial_runs, 5);
+        assert_eq!(map_elites.batch_size, 3);
+        assert_eq!(map_elites.storage, StorageBackend::Dense);
+        assert_eq!(map_elites.selection, Selection::Uniform);
+    }
+
+    #[test]
+    fn report_missing_config_file() {
+        let result = RuntimeBuilder::from_path(
+            "examples/missing/config.toml",
+            Box::new(|_| (0.0, vec![0.0])),
+        );
+
+        assert!(matches!(result, Err(Error::ConfigIo(_))));
+    }
+
+    #[test]
+    fn reject_invalid_parameters() {
+        let result = RuntimeBuilder::new(Box::new(|_| (0.0, vec![0.0])))
+            .map_resolution(10)
+            .feature_ranges(vec![FeatureRange::from((1.0, -1.0))])
+            .initial_runs(0)
+            .batch_size(3)
+            .build();
+
+        match result {
+            Err(Error::InvalidParameters(problems)) => assert_eq!(
+                problems,
+                vec![
+                    "map_elites.initial_runs: has to be positive",
+                    "map_elites.feature_ranges[0]: min 1 is not below max -1",
+                ]
+            ),
+            _ => panic!("parameters were not rejected"),
+        }
+    }
+
+    #[test]
+    fn reject_non_finite_search_settings() {
+        let result = RuntimeBuilder::new(Box::new(|_| (0.0, vec![0.0])))
+            .map_resolution(10)
+            .feature_ranges(vec![FeatureRange::from((-1.0, 1.0))])
+            .initial_runs(5)
+            .batch_size(3)
+            .bandit(Bandit {
+                exploration: f64::NAN,
+                ..Default::default()
+            })
+            .evaluation_timeout(EvaluationTimeout {
+                milliseconds: 100,
+                penalty_fitness: Some(f64::NEG_INFINITY),
+            })
+            .build();
+
+        match result {
+            Err(Error::InvalidParameters(problems)) => assert_eq!(
+                problems,
+                vec![
+                    "map_elites.bandit.exploration: NaN is not a finite non-negative number",
+                    "map_elites.evaluation_timeout.penalty_fitness: -inf is not finite",
+                ]
+            ),
+            _ => panic!("parameters were not rejected"),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+use crate::individual::Individual;
+
+/// Derives feature ranges from the behaviors of the evaluated initial population.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Calibration {
+    /// percentile in [0, 100] of observed values used as feature minimum
+    pub lower_percentile: f64,
+    /// percentile in [0, 100] of observed values used as feature maximum
+    pub upper_percentile: f64,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self {
+            lower_percentile: 1.0,
+            upper_percentile: 99.0,
+        }
+    }
+}
+
+impl Calibration {
+    /// Features whose observed values do not spread keep their configured range.
+    pub fn feature_ranges(
+        &self,
+        individuals: &[Individual],
+        configured_ranges: &[(f64, f64)],
+    ) -> Vec<(f64, f64)> {
+        configured_ranges
+            .iter()
+            .enumerate()
+            .map(|(feature, &configured_range)| {
+                let mut values: Vec<f64> = individuals
+                    .iter()
+                    .filter_map(|individual| individual.behavior.get(feature))
+                    .cloned()
+                    .collect();
+
+                if values.is_empty() {
+                    return configured_range;
+                }
+
+                values.sort_by(|a, b| a.partial_cmp(b).expect("could not compare floats"));
+
+                let feature_min = percentile(&values, self.lower_percentile);
+                let feature_max = percentile(&values, self.upper_percentile);
+
+                if feature_min < feature_max {
+                    (feature_min, feature_max)
+                } else {
+                    tracing::warn!(
+                        "feature {} did not spread in initial population, keeping configured range {:?}",
+                        feature,
+                        configured_range
+                    );
+                    configured_range
+                }
+            })
+            .collect()
+    }
+}
+
+/// Linearly interpolates between the closest ranks of the sorted `values`.
+pub fn percentile(values: &[f64], percentile: f64) -> f64 {
+    let rank = percentile.clamp(0.0, 100.0) / 100.0 * (values.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+
+    values[lower] + (values[upper] - values[lower]) * (rank - lower as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Calibration;
+    use crate::individual::Individual;
+
+    fn individuals(behaviors: Vec<Vec<f64>>) -> Vec<Individual> {
+        behaviors
+            .into_iter()
+            .map(|behavior| Individual {
+                behavior,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn calibrate_from_percentiles() {
+        let calibration = Calibration {
+            lower_percentile: 10.0,
+            upper_percentile: 90.0,
+        };
+
+        let individuals = individuals((0..=10).map(|value| vec![value as f64]).collect());
+
+        assert_eq!(
+            calibration.feature_ranges(&individuals, &[(-5.0, 5.0)]),
+            vec![(1.0, 9.0)]
+        );
+    }
+
+    #[test]
+    fn keep_configured_range_without_spread() {
+        let calibration = Calibration::default();
+
+        let individuals = individuals(vec![vec![0.0, 1.0], vec![0.0, 3.0]]);
+
+        assert_eq!(
+            calibration.feature_ranges(&individuals, &[(0.0, 10.0), (0.0, 10.0)]),
+            vec![(0.0, 10.0), (1.02, 2.98)]
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use set_genome::{GenomeContext, IdGenerator, Parameters as GenomeParameters};
+
+use crate::{
+    emitter::Scheduler,
+    error::Error,
+    individual::{next_gene_id, Individual},
+};
+
+/// Where and how often the state of a running search is written to disk.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// file holding the most recent checkpoint, replaced on every write
+    pub path: String,
+    /// number of batches between checkpoints
+    pub frequency: usize,
+}
+
+/// What a `GenomeContext` needs to continue, set_genome does not serialize the context itself.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) struct GenomeState {
+    /// seeds the random state of the restored context
+    pub seed: u64,
+    /// first gene id not taken by a stored genome
+    pub next_gene_id: u64,
+}
+
+impl GenomeState {
+    /// Draws the seed from `genome_context`, which has to be replaced by the restored state
+    /// for the running search to continue like a resumed one.
+    pub fn capture<'a>(
+        genome_context: &mut GenomeContext,
+        individuals: impl Iterator<Item = &'a Individual>,
+    ) -> Self {
+        Self {
+            seed: genome_context.rng.gen(),
+            next_gene_id: next_gene_id(individuals).unwrap_or_default(),
+        }
+    }
+
+    pub fn restore(&self, genome_parameters: &GenomeParameters) -> GenomeContext {
+        let mut genome_parameters = genome_parameters.clone();
+        genome_parameters.seed = Some(self.seed);
+
+        let mut genome_context = GenomeContext::new(genome_parameters);
+        genome_context.id_gen = IdGenerator::new(self.next_gene_id);
+        genome_context
+    }
+}
+
+/// Everything a `RuntimeIterator` needs to continue, besides the parameters and the fitness function.
+#[derive(Serialize)]
+pub(crate) struct SearchStateRef<'a, A> {
+    pub batches: usize,
+    pub archive: &'a A,
+    pub genome_state: GenomeState,
+    pub scheduler: &'a Scheduler,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SearchState<A> {
+    pub batches: usize,
+    pub archive: A,
+    pub genome_state: GenomeState,
+    pub scheduler: Scheduler,
+}
+
+impl<A: Serialize> SearchStateRef<'_, A> {
+    /// Writes to a temporary file first, so a crash while writing keeps the previous checkpoint intact.
+    pub fn write(&self, path: &str) -> Result<(), Error> {
+        let temporary_path = format!("{}.tmp", path);
+
+        let mut writer =
+            BufWriter::new(File::create(&temporary_path).map_err(Error::CheckpointIo)?);
+        serde_json::to_writer(&mut writer, self).map_err(Error::CheckpointFormat)?;
+        writer.flush().map_err(Error::CheckpointIo)?;
+
+        fs::rename(&temporary_path, path).map_err(Error::CheckpointIo)
+    }
+}
+
+impl<A: for<'de> Deserialize<'de>> SearchState<A> {
+    pub fn read(path: &str) -> Result<Self, Error> {
+        let reader = BufReader::new(File::open(path).map_err(Error::CheckpointIo)?);
+
+        serde_json::from_reader(reader).map_err(Error::CheckpointFormat)
+    }
+}
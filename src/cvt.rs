@@ -0,0 +1,82 @@
+use rand::Rng;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+/// Approximates a centroidal voronoi tessellation of the space spanned by `feature_ranges`
+/// by running Lloyd's k-means on uniformly drawn samples.
+pub fn centroids(
+    number_of_centroids: usize,
+    feature_ranges: &[(f64, f64)],
+    samples: usize,
+    iterations: usize,
+    rng: &mut impl Rng,
+) -> Vec<Vec<f64>> {
+    assert!(
+        samples >= number_of_centroids,
+        "need at least as many samples as centroids"
+    );
+
+    let samples: Vec<Vec<f64>> = (0..samples)
+        .map(|_| {
+            feature_ranges
+                .iter()
+                .map(|&(feature_min, feature_max)| rng.gen_range(feature_min..=feature_max))
+                .collect()
+        })
+        .collect();
+
+    // samples are random already so the first ones are a fine initial guess
+    let mut centroids: Vec<Vec<f64>> = samples[..number_of_centroids].to_vec();
+
+    for _ in 0..iterations {
+        let mut sums = vec![vec![0.0; feature_ranges.len()]; number_of_centroids];
+        let mut counts = vec![0usize; number_of_centroids];
+
+        // the assignment dominates the cost, summing up stays sequential to keep the result independent of threads
+        let closest: Vec<usize> = samples
+            .par_iter()
+            .map(|sample| nearest(&centroids, sample))
+            .collect();
+
+        for (sample, closest) in samples.iter().zip(closest) {
+            for (sum, value) in sums[closest].iter_mut().zip(sample.iter()) {
+                *sum += value;
+            }
+            counts[closest] += 1;
+        }
+
+        let mut moved = false;
+
+        for ((centroid, sum), &count) in centroids.iter_mut().zip(sums.iter()).zip(counts.iter()) {
+            // centroids without any sample keep their position
+            if count == 0 {
+                continue;
+            }
+            for (value, sum) in centroid.iter_mut().zip(sum.iter()) {
+                let mean = sum / count as f64;
+                moved |= (*value - mean).abs() > f64::EPSILON;
+                *value = mean;
+            }
+        }
+
+        if !moved {
+            break;
+        }
+    }
+
+    centroids
+}
+
+/// Index of the point closest to `target` by euclidean distance.
+pub fn nearest(points: &[Vec<f64>], target: &[f64]) -> usize {
+    points
+        .iter()
+        .map(|point| squared_distance(point, target))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("could not compare floats"))
+        .map(|(index, _)| index)
+        .expect("no points given")
+}
+
+pub fn squared_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b.iter()).map(|(a, b)| (a - b).powi(2)).sum()
+}
@@ -1,170 +1,718 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 
-use rand::distributions::{Distribution, WeightedIndex};
-use rand::Rng;
+use rand::seq::SliceRandom;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
+use crate::archive::{Archive, Insertion};
+use crate::binning::Binning;
+use crate::calibration;
+use crate::cvt;
+use crate::error::Error;
 use crate::individual::Individual;
+use crate::selection::{Candidates, Selection, Selector};
+use crate::storage::{Storage, StorageBackend};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ElitesMap {
-    map: HashMap<Vec<usize>, Individual>,
-    resolution: usize,
+    storage: Storage,
+    cells: Cells,
+    cell_capacity: CellCapacity,
+    // state for random replacement and behavior sampling, kept as plain seed to stay serializable
+    rng_state: u64,
     feature_ranges: Vec<(f64, f64)>,
+    out_of_range_policy: OutOfRange,
+    out_of_range_counts: OutOfRangeCounts,
+    observed_behaviors: Option<ObservedBehaviors>,
+    selection: Selection,
+    next_id: u64,
+    // by id of stored individuals
+    curiosity: HashMap<u64, f64>,
+}
+
+/// What happens to individuals with behavior outside the feature ranges.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutOfRange {
+    /// place into the closest edge cell
+    #[default]
+    Clamp,
+    /// discard the individual
+    Reject,
+    /// extend the feature ranges to include the behavior and re-place all elites,
+    /// voronoi cells can not grow and clamp instead
+    Grow,
+}
+
+/// How many individuals a cell holds and which one gives way to a newcomer once it is full.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct CellCapacity {
+    pub size: usize,
+    pub replacement: Replacement,
+}
+
+impl Default for CellCapacity {
+    fn default() -> Self {
+        Self {
+            size: 1,
+            replacement: Replacement::Worst,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Replacement {
+    /// keep the fittest individuals, a newcomer has to be at least as fit as the least fit one
+    #[default]
+    Worst,
+    /// a newcomer always replaces a random individual, as in Deep-Grid MAP-Elites
+    Random,
+}
+
+/// Periodically replaces the grid chunks by quantiles of all behaviors placed so far,
+/// so that every chunk of a feature holds roughly the same number of observations.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SlidingBoundaries {
+    /// number of placed individuals between recomputing the chunk edges
+    pub remap_frequency: usize,
+}
+
+/// Number of behaviors kept to estimate the quantiles of each feature.
+const OBSERVED_SAMPLE_SIZE: usize = 10_000;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct ObservedBehaviors {
+    remap_frequency: usize,
+    observed_since_remap: usize,
+    // total number of behaviors seen, the sample holds a uniform subset of them
+    observed: u64,
+    // reservoir sample of observed values per feature
+    values: Vec<Vec<f64>>,
+}
+
+impl ObservedBehaviors {
+    fn observe(&mut self, behavior: &[f64], rng_state: &mut u64) {
+        self.observed += 1;
+
+        let slot = if self.observed <= OBSERVED_SAMPLE_SIZE as u64 {
+            None
+        } else {
+            let mut rng = SmallRng::seed_from_u64(*rng_state);
+            let slot = rng.gen_range(0..self.observed);
+            *rng_state = rng.gen();
+            if slot >= OBSERVED_SAMPLE_SIZE as u64 {
+                return;
+            }
+            Some(slot as usize)
+        };
+
+        for (values, &feature_value) in self.values.iter_mut().zip(behavior.iter()) {
+            match slot {
+                Some(slot) => values[slot] = feature_value,
+                None => values.push(feature_value),
+            }
+        }
+    }
+}
+
+/// Number of placed individuals that were out of range, by how they were handled.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+pub struct OutOfRangeCounts {
+    pub clamped: usize,
+    pub rejected: usize,
+    pub grown: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+enum Cells {
+    Grid {
+        // number of cells per feature
+        resolutions: Vec<usize>,
+        binnings: Vec<Binning>,
+    },
+    Voronoi {
+        centroids: Vec<Vec<f64>>,
+        // indices of the closest other centroids for every centroid
+        neighborhoods: Vec<Vec<usize>>,
+    },
+    Unstructured {
+        // minimal euclidean distance between stored behaviors
+        threshold: f64,
+        // every accepted novel behavior opens a cell with the next index
+        next_cell: usize,
+    },
+}
+
+impl Cells {
+    /// Number of chunks per dimension, unbounded for unstructured cells.
+    fn shape(&self) -> Option<Vec<usize>> {
+        match self {
+            Cells::Grid { resolutions, .. } => Some(resolutions.clone()),
+            Cells::Voronoi { centroids, .. } => Some(vec![centroids.len()]),
+            Cells::Unstructured { .. } => None,
+        }
+    }
 }
 
 impl ElitesMap {
     pub fn new(resolution: usize, feature_ranges: Vec<(f64, f64)>) -> Self {
+        Self::with_resolutions(vec![resolution; feature_ranges.len()], feature_ranges)
+    }
+
+    pub fn with_resolutions(resolutions: Vec<usize>, feature_ranges: Vec<(f64, f64)>) -> Self {
+        let binnings = vec![Binning::Linear; feature_ranges.len()];
+        Self::with_binnings(binnings, resolutions, feature_ranges)
+    }
+
+    /// Features binned by explicit edges take their range and resolution from the edges.
+    pub fn with_binnings(
+        binnings: Vec<Binning>,
+        mut resolutions: Vec<usize>,
+        mut feature_ranges: Vec<(f64, f64)>,
+    ) -> Self {
+        assert!(
+            binnings.len() == feature_ranges.len() && resolutions.len() == feature_ranges.len(),
+            "binnings or resolutions did not match feature ranges"
+        );
+
+        for ((binning, resolution), feature_range) in binnings
+            .iter()
+            .zip(resolutions.iter_mut())
+            .zip(feature_ranges.iter_mut())
+        {
+            if let Binning::Edges(edges) = binning {
+                assert!(edges.len() >= 2, "need at least two edges per feature");
+                *resolution = edges.len() - 1;
+                *feature_range = (edges[0], edges[edges.len() - 1]);
+            }
+        }
+
+        assert!(
+            grid_capacity(&resolutions).is_some(),
+            "number of cells exceeds the addressable memory"
+        );
+
         Self {
-            map: HashMap::new(),
-            resolution,
+            storage: Storage::new(StorageBackend::Sparse, None),
+            cell_capacity: CellCapacity::default(),
+            rng_state: 0,
+            cells: Cells::Grid {
+                resolutions,
+                binnings,
+            },
             feature_ranges,
+            out_of_range_policy: OutOfRange::default(),
+            out_of_range_counts: OutOfRangeCounts::default(),
+            observed_behaviors: None,
+            selection: Selection::default(),
+            next_id: 0,
+            curiosity: HashMap::new(),
         }
     }
 
-    // #[tracing::instrument]
-    pub fn place_individual(&mut self, individual: Individual) {
+    pub fn with_centroids(centroids: Vec<Vec<f64>>, feature_ranges: Vec<(f64, f64)>) -> Self {
+        assert!(!centroids.is_empty(), "need at least one centroid");
         assert!(
-            individual.behavior.len() == self.feature_ranges.len(),
-            "behavior descriptor did not match features ranges"
+            centroids
+                .iter()
+                .all(|centroid| centroid.len() == feature_ranges.len()),
+            "centroids did not match feature ranges"
         );
 
-        let cell_index: Vec<usize> = individual
-            .behavior
+        // consider as many neighbors as an inner grid cell would have
+        let neighborhood_size = (2 * feature_ranges.len()).min(centroids.len() - 1);
+
+        let neighborhoods = centroids
             .iter()
             .enumerate()
-            .zip(self.feature_ranges.iter())
-            .map(|((index, mut feature_value), (feature_min, feature_max))| {
-                // cap feature value to configured interval
-                if feature_value >= feature_max {
-                    tracing::warn!(
-                        "capping feature {} with value {} to feature_max {}",
-                        index,
-                        feature_value,
-                        feature_max,
-                    );
-                    feature_value = feature_max;
-                }
-                if feature_value < feature_min {
-                    tracing::warn!(
-                        "capping feature {} with value {} to feature_min {}",
-                        index,
-                        feature_value,
-                        feature_min,
-                    );
-                    feature_value = feature_min;
-                }
-
-                // add some epsilon to spanned range to have [min, max[ resolution
-                ((feature_value - feature_min) / (feature_max - feature_min + 1E-15)
-                    * self.resolution as f64)
-                    .floor() as usize
+            .map(|(index, centroid)| {
+                let mut others: Vec<(usize, f64)> = centroids
+                    .iter()
+                    .enumerate()
+                    .filter(|&(other_index, _)| other_index != index)
+                    .map(|(other_index, other)| {
+                        (other_index, cvt::squared_distance(centroid, other))
+                    })
+                    .collect();
+
+                others
+                    .sort_by(|(_, a), (_, b)| a.partial_cmp(b).expect("could not compare floats"));
+
+                others
+                    .into_iter()
+                    .take(neighborhood_size)
+                    .map(|(other_index, _)| other_index)
+                    .collect()
             })
             .collect();
 
-        self.map
-            .entry(cell_index)
-            .and_modify(|previous_individual| {
-                if previous_individual.fitness > individual.fitness {
-                    // fitness did not improve, do nothing
-                } else {
-                    *previous_individual = individual.clone();
-                }
-            })
-            .or_insert(individual);
+        Self {
+            storage: Storage::new(StorageBackend::Sparse, None),
+            cell_capacity: CellCapacity::default(),
+            rng_state: 0,
+            cells: Cells::Voronoi {
+                centroids,
+                neighborhoods,
+            },
+            feature_ranges,
+            out_of_range_policy: OutOfRange::default(),
+            out_of_range_counts: OutOfRangeCounts::default(),
+            observed_behaviors: None,
+            selection: Selection::default(),
+            next_id: 0,
+            curiosity: HashMap::new(),
+        }
     }
 
-    // ACTUALLY RANDOM
-    /* pub fn get_random_individual(&self, rng: &mut impl Rng) -> Individual {
-        self.map
-            .values()
-            .choose(rng)
-            .cloned()
-            .expect("map did not held any individual")
-    } */
+    pub fn centroidal_voronoi(
+        number_of_centroids: usize,
+        feature_ranges: Vec<(f64, f64)>,
+        samples: usize,
+        iterations: usize,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let centroids = cvt::centroids(
+            number_of_centroids,
+            &feature_ranges,
+            samples,
+            iterations,
+            rng,
+        );
 
-    // RANDOM BUT WEIGHTED BY GLOBAL FITNESS
-    /* pub fn get_random_individual(&self, rng: &mut impl Rng) -> Individual {
-        let individuals: Vec<&Individual> = self.map.values().collect();
-        let fitnesses: Vec<f64> = individuals
-            .iter()
-            .map(|individual| individual.fitness)
-            .collect();
+        Self::with_centroids(centroids, feature_ranges)
+    }
+
+    /// An archive without predefined cells and therefore without feature ranges.
+    /// An individual opens a new cell when its behavior is farther than `threshold` from every stored behavior
+    /// and otherwise competes with the closest stored individual.
+    /// Finding that individual scans every stored cell, so placement cost grows with the archive.
+    pub fn unstructured(threshold: f64) -> Self {
+        assert!(threshold > 0.0, "threshold has to be positive");
+
+        Self {
+            storage: Storage::new(StorageBackend::Sparse, None),
+            cell_capacity: CellCapacity::default(),
+            rng_state: 0,
+            cells: Cells::Unstructured {
+                threshold,
+                next_cell: 0,
+            },
+            feature_ranges: Vec::new(),
+            out_of_range_policy: OutOfRange::default(),
+            out_of_range_counts: OutOfRangeCounts::default(),
+            observed_behaviors: None,
+            selection: Selection::default(),
+            next_id: 0,
+            curiosity: HashMap::new(),
+        }
+    }
+
+    pub fn with_cell_capacity(mut self, cell_capacity: CellCapacity) -> Self {
+        assert!(cell_capacity.size > 0, "cells need to hold an individual");
+        self.cell_capacity = cell_capacity;
+        self
+    }
+
+    /// Dense storage suits grids where a large share of cells gets occupied,
+    /// unstructured archives only support sparse storage.
+    pub fn with_storage(mut self, backend: StorageBackend) -> Self {
+        assert!(
+            backend == StorageBackend::Sparse || !matches!(self.cells, Cells::Unstructured { .. }),
+            "unstructured archives need sparse storage"
+        );
+
+        let storage = Storage::new(backend, self.cells.shape().as_deref());
+        let stored_storage = std::mem::replace(&mut self.storage, storage);
+
+        for individual in stored_storage.into_individuals() {
+            self.insert(individual);
+        }
+        self
+    }
+
+    /// Seeds random replacement and the sample of observed behaviors, which otherwise start from the same state.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng_state = seed;
+        self
+    }
+
+    pub fn with_selection(mut self, selection: Selection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    pub fn with_out_of_range_policy(mut self, out_of_range_policy: OutOfRange) -> Self {
+        self.out_of_range_policy = out_of_range_policy;
+        self
+    }
+
+    /// Only applies to grid cells.
+    pub fn with_sliding_boundaries(mut self, sliding_boundaries: SlidingBoundaries) -> Self {
+        assert!(
+            sliding_boundaries.remap_frequency > 0,
+            "remap frequency has to be positive"
+        );
+
+        if let Cells::Voronoi { .. } | Cells::Unstructured { .. } = self.cells {
+            tracing::warn!("sliding boundaries are ignored for non-grid cells");
+            return self;
+        }
+
+        self.observed_behaviors = Some(ObservedBehaviors {
+            remap_frequency: sliding_boundaries.remap_frequency,
+            observed_since_remap: 0,
+            observed: 0,
+            values: vec![Vec::new(); self.feature_ranges.len()],
+        });
+        self
+    }
+
+    // #[tracing::instrument]
+    pub fn place_individual(&mut self, individual: Individual) -> Insertion {
+        self.try_place_individual(individual)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_place_individual(&mut self, mut individual: Individual) -> Result<Insertion, Error> {
+        if let Some(dimensions) = self.dimensions() {
+            if individual.behavior.len() != dimensions {
+                return Err(Error::DescriptorDimensionMismatch {
+                    expected: dimensions,
+                    found: individual.behavior.len(),
+                });
+            }
+        }
 
-        let mut min_fitness = f64::MAX;
+        // rejected behaviors count as well, otherwise the boundaries could never widen again
+        let remap_due = if let Some(observed_behaviors) = &mut self.observed_behaviors {
+            observed_behaviors.observe(&individual.behavior, &mut self.rng_state);
+            observed_behaviors.observed_since_remap += 1;
+            observed_behaviors.observed_since_remap >= observed_behaviors.remap_frequency
+        } else {
+            false
+        };
 
-        for &fitness in &fitnesses {
-            if fitness < min_fitness {
-                min_fitness = fitness;
+        if self.is_out_of_range(&individual.behavior) {
+            tracing::debug!(
+                "behavior {:?} is out of feature ranges {:?}",
+                individual.behavior,
+                self.feature_ranges
+            );
+
+            match (self.out_of_range_policy, &self.cells) {
+                (OutOfRange::Reject, _) => {
+                    self.out_of_range_counts.rejected += 1;
+                    if let Some(score) = individual
+                        .parent
+                        .and_then(|parent| self.curiosity.get_mut(&parent))
+                    {
+                        *score -= 1.0;
+                    }
+                    if remap_due {
+                        self.remap_boundaries();
+                    }
+                    return Ok(Insertion::Discarded { cell: None });
+                }
+                (OutOfRange::Grow, Cells::Grid { .. }) => {
+                    self.out_of_range_counts.grown += 1;
+                    self.grow_feature_ranges(&individual.behavior);
+                }
+                (OutOfRange::Clamp, _) | (OutOfRange::Grow, _) => {
+                    self.out_of_range_counts.clamped += 1;
+                }
             }
         }
 
-        let weights: Vec<f64> = fitnesses
-            .iter()
-            // .map(|&fitness| (fitness - min_fitness + 1.0).log2())
-            .map(|&fitness| (fitness - min_fitness))
-            .collect();
+        individual.id = self.next_id;
+        self.next_id += 1;
+        let parent = individual.parent;
+
+        let insertion = self.insert(individual);
+
+        // reward parents whose offspring get into the map
+        if let Some(score) = parent.and_then(|parent| self.curiosity.get_mut(&parent)) {
+            *score += if insertion.is_success() { 1.0 } else { -1.0 };
+        }
+
+        if remap_due {
+            self.remap_boundaries();
+        }
+
+        Ok(insertion)
+    }
+
+    /// Empties the map and returns its individuals ordered by id, the cells stay as they are.
+    pub fn take_individuals(&mut self) -> Vec<Individual> {
+        let storage = Storage::new(self.storage.backend(), self.cells.shape().as_deref());
+        let mut individuals = std::mem::replace(&mut self.storage, storage).into_individuals();
+        self.curiosity.clear();
+
+        individuals.sort_by_key(|individual| individual.id);
+        individuals
+    }
+
+    /// Whether an individual with `behavior` only gets stored by beating the fitness of a stored one,
+    /// i.e. its cell is full and replaces the worst.
+    pub fn competes_on_fitness(&self, behavior: &[f64]) -> bool {
+        if self.cell_capacity.replacement != Replacement::Worst {
+            return false;
+        }
+
+        let cell_index = match self.cells {
+            Cells::Unstructured { threshold, .. } => match self.nearby_cell(behavior, threshold) {
+                Some(position) => position,
+                None => return false,
+            },
+            _ => self.cell_index(behavior),
+        };
+
+        self.storage
+            .get(&cell_index)
+            .is_some_and(|cell| cell.len() >= self.cell_capacity.size)
+    }
+
+    /// Puts the individual into its cell according to the cell capacity.
+    fn insert(&mut self, individual: Individual) -> Insertion {
+        let cell_index = match self.cells {
+            Cells::Unstructured {
+                threshold,
+                next_cell,
+            } => match self.nearby_cell(&individual.behavior, threshold) {
+                Some(position) => position,
+                None => {
+                    self.cells = Cells::Unstructured {
+                        threshold,
+                        next_cell: next_cell + 1,
+                    };
+                    vec![next_cell]
+                }
+            },
+            _ => self.cell_index(&individual.behavior),
+        };
 
-        let dist = WeightedIndex::new(&weights).unwrap();
+        let size = self.cell_capacity.size;
+        let id = individual.id;
+        let cell = self.storage.cell_mut(cell_index.clone());
 
-        individuals[dist.sample(rng)].clone()
-    } */
+        if cell.is_empty() {
+            cell.push(individual);
+            self.curiosity.entry(id).or_insert(0.0);
+            return Insertion::NewCell { cell: cell_index };
+        }
+
+        if cell.len() < size && self.cell_capacity.replacement == Replacement::Random {
+            cell.push(individual);
+            self.curiosity.entry(id).or_insert(0.0);
+            return Insertion::Joined { cell: cell_index };
+        }
+
+        match self.cell_capacity.replacement {
+            Replacement::Worst => {
+                // cells are sorted by descending fitness, newcomers win ties
+                let rank = cell
+                    .iter()
+                    .position(|elite| elite.fitness <= individual.fitness)
+                    .unwrap_or(cell.len());
+
+                if rank >= size {
+                    // stored individuals can get discarded while rebinning
+                    self.curiosity.remove(&id);
+                    return Insertion::Discarded {
+                        cell: Some(cell_index),
+                    };
+                }
+
+                let fitness = individual.fitness;
+                cell.insert(rank, individual);
+                self.curiosity.entry(id).or_insert(0.0);
+
+                if cell.len() > size {
+                    let replaced = cell.pop().expect("cell did not held any individual");
+                    self.curiosity.remove(&replaced.id);
+                    Insertion::Replaced {
+                        cell: cell_index,
+                        fitness_delta: fitness - replaced.fitness,
+                    }
+                } else {
+                    Insertion::Joined { cell: cell_index }
+                }
+            }
+            Replacement::Random => {
+                let mut rng = SmallRng::seed_from_u64(self.rng_state);
+                let replaced = rng.gen_range(0..cell.len());
+                self.rng_state = rng.gen();
+                let fitness_delta = individual.fitness - cell[replaced].fitness;
+                let replaced = std::mem::replace(&mut cell[replaced], individual);
+                self.curiosity.remove(&replaced.id);
+                self.curiosity.entry(id).or_insert(0.0);
+                Insertion::Replaced {
+                    cell: cell_index,
+                    fitness_delta,
+                }
+            }
+        }
+    }
 
-    // RANDOM BUT WEIGHTED BY DOMINATED NEIGHBORS
     pub fn get_random_individual(&self, rng: &mut impl Rng) -> Individual {
-        let individuals: Vec<(&Vec<usize>, &Individual)> = self.map.iter().collect();
+        self.try_get_random_individual(rng)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        let weights: Vec<f64> = individuals
-            .iter()
-            .map(|(position, individual)| {
-                let neighbors_count = self.neighbors(position).count() as f64;
-                let dominated_neighbors_count = self
-                    .neighbors(position)
-                    .filter(|neighbor| individual.fitness > neighbor.fitness)
-                    .count() as f64;
-                // always count oneself in as dominated to produce non-zero weight
-                (dominated_neighbors_count + 1.0) / (neighbors_count + 1.0)
-            })
-            .collect();
+    pub fn try_get_random_individual(&self, rng: &mut impl Rng) -> Result<Individual, Error> {
+        if self.is_empty() {
+            return Err(Error::EmptyArchive);
+        }
 
-        /* if weights.iter().any(|&w| w < 1.0) {
-            dbg!(&weights);
-        } */
+        Ok(self
+            .get_random_individuals(1, rng)
+            .pop()
+            .expect("drew one individual"))
+    }
 
-        let individuals: Vec<&Individual> = individuals
-            .iter()
-            .map(|&(_, individual)| individual)
-            .collect();
+    /// Draws `count` parents with the configured selection, computed once for all of them.
+    /// The returned copies reference the stored individuals as their parent.
+    pub fn get_random_individuals(&self, count: usize, rng: &mut impl Rng) -> Vec<Individual> {
+        self.get_random_individuals_with(&self.selection, count, rng)
+    }
 
-        let dist = WeightedIndex::new(&weights).unwrap();
+    pub fn get_random_individuals_with(
+        &self,
+        selector: &impl Selector,
+        count: usize,
+        rng: &mut impl Rng,
+    ) -> Vec<Individual> {
+        assert!(!self.is_empty(), "map did not held any individual");
+
+        let mut cells: Vec<_> = self.storage.cells().collect();
+        // hash map order depends on the map's history, which a map restored from a checkpoint does not share
+        if self.storage.backend() == StorageBackend::Sparse {
+            cells.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        }
 
-        individuals[dist.sample(rng)].clone()
+        let candidates = Candidates::new(self, cells);
+
+        selector
+            .select(&candidates, count, rng)
+            .into_iter()
+            .map(|index| {
+                // sample uniformly within the selected cell
+                let parent = candidates
+                    .cell(index)
+                    .choose(rng)
+                    .expect("cell did not held any individual");
+
+                Individual {
+                    parent: Some(parent.id),
+                    ..parent.clone()
+                }
+            })
+            .collect()
     }
 
+    /// Sets the same resolution for every feature.
     pub fn update_resolution(&mut self, resolution: usize) {
-        let stored_individuals = std::mem::replace(&mut self.map, HashMap::new());
+        self.update_resolutions(vec![resolution; self.feature_ranges.len()]);
+    }
+
+    /// Only affects grid cells, voronoi cells are fixed by their centroids
+    /// and features binned by explicit edges keep their resolution unless
+    /// their edges are managed by sliding boundaries.
+    pub fn update_resolutions(&mut self, resolutions: Vec<usize>) {
+        if let Cells::Voronoi { .. } | Cells::Unstructured { .. } = self.cells {
+            return;
+        }
+
+        assert!(
+            resolutions.len() == self.feature_ranges.len(),
+            "resolutions did not match feature ranges"
+        );
+
+        assert!(
+            grid_capacity(&resolutions).is_some(),
+            "number of cells exceeds the addressable memory"
+        );
 
-        self.resolution = resolution;
+        if let Cells::Grid {
+            resolutions: ref mut current_resolutions,
+            ref binnings,
+        } = self.cells
+        {
+            for ((current_resolution, resolution), binning) in current_resolutions
+                .iter_mut()
+                .zip(resolutions)
+                .zip(binnings.iter())
+            {
+                // explicit edges keep their count until observations replace them
+                if matches!(binning, Binning::Edges(_))
+                    && self
+                        .observed_behaviors
+                        .as_ref()
+                        .is_none_or(|observed_behaviors| observed_behaviors.observed == 0)
+                {
+                    continue;
+                }
+                *current_resolution = resolution;
+            }
+        }
 
-        for (_, individual) in stored_individuals {
-            self.place_individual(individual);
+        if self.observed_behaviors.is_some() {
+            self.remap_boundaries();
+            return;
         }
+
+        self.rebin();
+    }
+
+    /// A copy of a random elite neighboring the cell of the individual's parent.
+    pub fn get_random_neighbor(
+        &self,
+        individual: &Individual,
+        rng: &mut impl Rng,
+    ) -> Option<Individual> {
+        let parent = individual.parent?;
+
+        let position = match self.cells {
+            Cells::Unstructured { .. } => self
+                .storage
+                .cells()
+                .find(|(_, cell)| cell.iter().any(|stored| stored.id == parent))?
+                .0
+                .into_owned(),
+            _ => self.cell_index(&individual.behavior),
+        };
+
+        // the parent might have been evicted from its cell in the meantime
+        self.storage.get(&position)?;
+
+        let neighbors: Vec<&Individual> = self.neighbors(&position).collect();
+
+        neighbors.choose(rng).map(|&neighbor| Individual {
+            parent: Some(neighbor.id),
+            ..neighbor.clone()
+        })
     }
 
     pub fn top_individual(&self) -> Individual {
-        self.map
-            .values()
+        self.try_top_individual()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_top_individual(&self) -> Result<Individual, Error> {
+        self.individuals()
             .max_by(|a, b| {
                 a.fitness
                     .partial_cmp(&b.fitness)
                     .expect("could not compare floats")
             })
             .cloned()
-            .expect("map did not held any individual")
+            .ok_or(Error::EmptyArchive)
     }
 
     pub fn sorted_individuals(&self) -> Vec<&Individual> {
-        let mut individuals: Vec<&Individual> = self.map.values().collect();
+        let mut individuals: Vec<&Individual> = self.individuals().collect();
 
         individuals.sort_by(|a, b| {
             b.fitness
@@ -175,17 +723,300 @@ impl ElitesMap {
         individuals
     }
 
+    /// Number of occupied cells.
     pub fn len(&self) -> usize {
-        self.map.len()
+        self.storage.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.storage.is_empty()
+    }
+
+    /// All stored individuals, cells may hold several.
+    pub fn individuals(&self) -> impl Iterator<Item = &Individual> {
+        self.storage.individuals()
+    }
+
+    pub fn feature_ranges(&self) -> &[(f64, f64)] {
+        &self.feature_ranges
+    }
+
+    /// Offspring of the stored individual that got into the map minus those that got discarded.
+    pub fn curiosity(&self, individual: &Individual) -> Option<f64> {
+        self.curiosity.get(&individual.id).cloned()
+    }
+
+    pub fn out_of_range_counts(&self) -> OutOfRangeCounts {
+        self.out_of_range_counts
     }
 
     pub fn capacity(&self) -> usize {
-        self.resolution.pow(self.feature_ranges.len() as u32)
+        match &self.cells {
+            Cells::Grid { resolutions, .. } => {
+                grid_capacity(resolutions).expect("number of cells exceeds the addressable memory")
+            }
+            Cells::Voronoi { centroids, .. } => centroids.len(),
+            // unbounded
+            Cells::Unstructured { .. } => usize::MAX,
+        }
     }
 }
 
 impl ElitesMap {
-    fn neighbors<'a>(&'a self, position: &'a Vec<usize>) -> impl Iterator<Item = &'a Individual> {
+    /// Length of behavior descriptors, unknown for an empty unstructured archive.
+    fn dimensions(&self) -> Option<usize> {
+        match self.cells {
+            Cells::Unstructured { .. } => self
+                .individuals()
+                .next()
+                .map(|individual| individual.behavior.len()),
+            _ => Some(self.feature_ranges.len()),
+        }
+    }
+
+    fn is_out_of_range(&self, behavior: &[f64]) -> bool {
+        behavior.iter().zip(self.feature_ranges.iter()).any(
+            |(&feature_value, &(feature_min, feature_max))| {
+                feature_value < feature_min || feature_value > feature_max
+            },
+        )
+    }
+
+    fn grow_feature_ranges(&mut self, behavior: &[f64]) {
+        for (index, (&feature_value, (feature_min, feature_max))) in behavior
+            .iter()
+            .zip(self.feature_ranges.iter_mut())
+            .enumerate()
+        {
+            *feature_min = feature_min.min(feature_value);
+            *feature_max = feature_max.max(feature_value);
+
+            // explicit edges are stretched at the outermost chunks
+            if let Cells::Grid { binnings, .. } = &mut self.cells {
+                if let Binning::Edges(edges) = &mut binnings[index] {
+                    let last = edges.len() - 1;
+                    edges[0] = *feature_min;
+                    edges[last] = *feature_max;
+                }
+            }
+        }
+
+        self.rebin();
+    }
+
+    fn remap_boundaries(&mut self) {
+        let observed_behaviors = match &mut self.observed_behaviors {
+            Some(observed_behaviors) => observed_behaviors,
+            None => return,
+        };
+
+        observed_behaviors.observed_since_remap = 0;
+
+        // without observations there are no quantiles, the current edges stay
+        if observed_behaviors.observed == 0 {
+            self.rebin();
+            return;
+        }
+
+        if let Cells::Grid {
+            resolutions,
+            binnings,
+        } = &mut self.cells
+        {
+            for (((values, &resolution), binning), feature_range) in observed_behaviors
+                .values
+                .iter()
+                .zip(resolutions.iter())
+                .zip(binnings.iter_mut())
+                .zip(self.feature_ranges.iter_mut())
+            {
+                // the sample itself stays unordered, slots are replaced at random
+                let mut values = values.clone();
+                values.sort_by(|a, b| a.partial_cmp(b).expect("could not compare floats"));
+
+                let edges: Vec<f64> = (0..=resolution)
+                    .map(|chunk| {
+                        calibration::percentile(&values, chunk as f64 / resolution as f64 * 100.0)
+                    })
+                    .collect();
+
+                *feature_range = (edges[0], edges[resolution]);
+                *binning = Binning::Edges(edges);
+            }
+        }
+
+        tracing::debug!("remapped feature ranges to {:?}", self.feature_ranges);
+
+        self.rebin();
+    }
+
+    /// Places all stored individuals again, cells that collide compete for capacity.
+    fn rebin(&mut self) {
+        let storage = Storage::new(self.storage.backend(), self.cells.shape().as_deref());
+        let stored_storage = std::mem::replace(&mut self.storage, storage);
+
+        for individual in stored_storage.into_individuals() {
+            self.insert(individual);
+        }
+    }
+
+    /// The fittest individual of the cell at `position`.
+    fn elite(&self, position: &[usize]) -> Option<&Individual> {
+        self.storage.get(position).map(cell_elite)
+    }
+
+    /// Stored cell of an unstructured archive with the elite closest to `behavior`, if it is within `threshold`.
+    fn nearby_cell(&self, behavior: &[f64], threshold: f64) -> Option<Vec<usize>> {
+        self.storage
+            .cells()
+            .map(|(position, cell)| {
+                (
+                    position,
+                    cvt::squared_distance(&cell_elite(cell).behavior, behavior),
+                )
+            })
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).expect("could not compare floats"))
+            .filter(|&(_, squared_distance)| squared_distance <= threshold.powi(2))
+            .map(|(position, _)| position.into_owned())
+    }
+
+    fn cell_index(&self, behavior: &[f64]) -> Vec<usize> {
+        match &self.cells {
+            Cells::Grid {
+                resolutions,
+                binnings,
+            } => self.grid_cell_index(behavior, resolutions, binnings),
+            Cells::Voronoi { centroids, .. } => {
+                let clamped_behavior: Vec<f64> = behavior
+                    .iter()
+                    .zip(self.feature_ranges.iter())
+                    .map(|(&feature_value, &(feature_min, feature_max))| {
+                        feature_value.max(feature_min).min(feature_max)
+                    })
+                    .collect();
+
+                vec![cvt::nearest(centroids, &clamped_behavior)]
+            }
+            Cells::Unstructured { .. } => {
+                unreachable!("unstructured cells depend on stored individuals")
+            }
+        }
+    }
+
+    fn grid_cell_index(
+        &self,
+        behavior: &[f64],
+        resolutions: &[usize],
+        binnings: &[Binning],
+    ) -> Vec<usize> {
+        behavior
+            .iter()
+            .zip(self.feature_ranges.iter())
+            .zip(resolutions.iter().zip(binnings.iter()))
+            .map(
+                |((&feature_value, &(feature_min, feature_max)), (&resolution, binning))| {
+                    // cap feature value to configured interval, out of range policy is applied beforehand
+                    let feature_value = feature_value.max(feature_min).min(feature_max);
+
+                    binning.chunk(feature_value, feature_min, feature_max, resolution)
+                },
+            )
+            .collect()
+    }
+
+    pub(crate) fn neighbors<'a>(
+        &'a self,
+        position: &'a [usize],
+    ) -> Box<dyn Iterator<Item = &'a Individual> + 'a> {
+        if let (Cells::Grid { .. }, Storage::Dense(dense)) = (&self.cells, &self.storage) {
+            return Box::new(dense.neighbors(position).map(cell_elite));
+        }
+
+        let neighbor_positions = match &self.cells {
+            Cells::Grid { resolutions, .. } => self.grid_neighbor_positions(position, resolutions),
+            Cells::Voronoi { neighborhoods, .. } => {
+                assert!(
+                    position.len() == 1 && position[0] < neighborhoods.len(),
+                    "requested neighbors for invalid position {:?}",
+                    position
+                );
+
+                neighborhoods[position[0]]
+                    .iter()
+                    .map(|&neighbor| vec![neighbor])
+                    .collect()
+            }
+            Cells::Unstructured { .. } => self.unstructured_neighbor_positions(position),
+        };
+
+        Box::new(
+            neighbor_positions
+                .into_iter()
+                .flat_map(move |neighbor_position| self.elite(&neighbor_position)),
+        )
+    }
+
+    /// The closest stored behaviors, as many as an inner grid cell would have neighbors.
+    fn unstructured_neighbor_positions(&self, position: &[usize]) -> Vec<Vec<usize>> {
+        let behavior = &self
+            .elite(position)
+            .unwrap_or_else(|| panic!("requested neighbors for invalid position {:?}", position))
+            .behavior;
+
+        nearest(
+            behavior,
+            self.storage
+                .cells()
+                .filter(|(other_position, _)| other_position.as_ref() != position)
+                .map(|(other_position, other)| {
+                    (other_position, cell_elite(other).behavior.as_slice())
+                }),
+            2 * behavior.len(),
+        )
+        .into_iter()
+        .map(Cow::into_owned)
+        .collect()
+    }
+
+    /// Neighborhoods of all `cells` at once as indices into `cells`, `None` unless the archive is unstructured.
+    /// Looking up neighbors cell by cell would scan every stored cell for each of them.
+    pub(crate) fn unstructured_neighborhoods(
+        &self,
+        cells: &[(Cow<[usize]>, &[Individual])],
+    ) -> Option<Vec<Vec<usize>>> {
+        if !matches!(self.cells, Cells::Unstructured { .. }) {
+            return None;
+        }
+
+        let behaviors: Vec<&[f64]> = cells
+            .iter()
+            .map(|(_, cell)| cell_elite(cell).behavior.as_slice())
+            .collect();
+
+        Some(
+            behaviors
+                .iter()
+                .enumerate()
+                .map(|(index, behavior)| {
+                    nearest(
+                        behavior,
+                        behaviors
+                            .iter()
+                            .enumerate()
+                            .filter(|&(other_index, _)| other_index != index)
+                            .map(|(other_index, &other)| (other_index, other)),
+                        2 * behavior.len(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn grid_neighbor_positions(
+        &self,
+        position: &[usize],
+        resolutions: &[usize],
+    ) -> Vec<Vec<usize>> {
         assert!(
             position.len() == self.feature_ranges.len(),
             "requested neighbors for invalid position {:?}",
@@ -193,42 +1024,145 @@ impl ElitesMap {
         );
 
         assert!(
-            position.iter().all(|&chunk| chunk < self.resolution),
+            position
+                .iter()
+                .zip(resolutions.iter())
+                .all(|(&chunk, &resolution)| chunk < resolution),
             "position contains invalid chunk index"
         );
 
-        (0..position.len())
-            .flat_map(move |feature| {
-                let maximum = self.resolution - 1;
-                let mut up = position.clone();
-                let mut down = position.clone();
+        resolutions
+            .iter()
+            .enumerate()
+            .flat_map(move |(feature, &resolution)| {
+                let mut neighbor_positions = Vec::new();
 
-                match position[feature] {
-                    0 => {
-                        up[feature] += 1;
-                        vec![up]
-                    }
-                    max_chunk if max_chunk == maximum => {
-                        down[feature] -= 1;
-                        vec![down]
-                    }
-                    _ => {
-                        down[feature] -= 1;
-                        up[feature] += 1;
-                        vec![up, down]
-                    }
+                if position[feature] + 1 < resolution {
+                    let mut up = position.to_vec();
+                    up[feature] += 1;
+                    neighbor_positions.push(up);
                 }
+                if position[feature] > 0 {
+                    let mut down = position.to_vec();
+                    down[feature] -= 1;
+                    neighbor_positions.push(down);
+                }
+
+                neighbor_positions
             })
-            .flat_map(move |neighbor_position| self.map.get(&neighbor_position))
+            .collect()
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use rand::prelude::ThreadRng;
+impl Archive for ElitesMap {
+    fn place_individual(&mut self, individual: Individual) -> Insertion {
+        ElitesMap::place_individual(self, individual)
+    }
+
+    fn try_place_individual(&mut self, individual: Individual) -> Result<Insertion, Error> {
+        ElitesMap::try_place_individual(self, individual)
+    }
+
+    fn competes_on_fitness(&self, behavior: &[f64]) -> bool {
+        ElitesMap::competes_on_fitness(self, behavior)
+    }
 
-    use super::ElitesMap;
+    fn get_random_individual(&self, rng: &mut impl Rng) -> Individual {
+        ElitesMap::get_random_individual(self, rng)
+    }
+
+    fn get_random_individuals(&self, count: usize, rng: &mut impl Rng) -> Vec<Individual> {
+        ElitesMap::get_random_individuals(self, count, rng)
+    }
+
+    fn get_random_individuals_with(
+        &self,
+        selector: &impl Selector,
+        count: usize,
+        rng: &mut impl Rng,
+    ) -> Vec<Individual> {
+        ElitesMap::get_random_individuals_with(self, selector, count, rng)
+    }
+
+    fn get_random_neighbor(
+        &self,
+        individual: &Individual,
+        rng: &mut impl Rng,
+    ) -> Option<Individual> {
+        ElitesMap::get_random_neighbor(self, individual, rng)
+    }
+
+    fn individuals(&self) -> Box<dyn Iterator<Item = &Individual> + '_> {
+        Box::new(ElitesMap::individuals(self))
+    }
+
+    fn len(&self) -> usize {
+        ElitesMap::len(self)
+    }
+
+    fn capacity(&self) -> usize {
+        ElitesMap::capacity(self)
+    }
+
+    fn top_individual(&self) -> Individual {
+        ElitesMap::top_individual(self)
+    }
+}
+
+/// `None` if the number of cells overflows.
+fn grid_capacity(resolutions: &[usize]) -> Option<usize> {
+    resolutions
+        .iter()
+        .try_fold(1usize, |cells, &resolution| cells.checked_mul(resolution))
+}
+
+/// The `count` entries whose behavior is closest to `behavior`, closest first.
+fn nearest<'a, T>(
+    behavior: &[f64],
+    others: impl Iterator<Item = (T, &'a [f64])>,
+    count: usize,
+) -> Vec<T> {
+    let mut others: Vec<(T, f64)> = others
+        .map(|(other, other_behavior)| (other, cvt::squared_distance(other_behavior, behavior)))
+        .collect();
+
+    let by_distance =
+        |(_, a): &(T, f64), (_, b): &(T, f64)| a.partial_cmp(b).expect("could not compare floats");
+
+    // partition first so only the kept entries get sorted
+    if count < others.len() {
+        others.select_nth_unstable_by(count, by_distance);
+        others.truncate(count);
+    }
+    others.sort_by(by_distance);
+
+    others.into_iter().map(|(other, _)| other).collect()
+}
+
+pub(crate) fn cell_elite(cell: &[Individual]) -> &Individual {
+    cell.iter()
+        .max_by(|a, b| {
+            a.fitness
+                .partial_cmp(&b.fitness)
+                .expect("could not compare floats")
+        })
+        .expect("cell did not held any individual")
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::ThreadRng;
+    use rand::Rng;
+
+    use super::{
+        CellCapacity, ElitesMap, OutOfRange, OutOfRangeCounts, Replacement, SlidingBoundaries,
+        OBSERVED_SAMPLE_SIZE,
+    };
+    use crate::archive::{Archive, Insertion};
+    use crate::binning::Binning;
+    use crate::error::Error;
     use crate::individual::Individual;
+    use crate::storage::StorageBackend;
     #[test]
     fn place_and_retrieve() {
         let mut elites_map = ElitesMap::new(4, vec![(-5.0, 5.0)]);
@@ -241,242 +1175,990 @@ mod tests {
 
         elites_map.place_individual(individual);
 
+        assert_eq!(
+            elites_map.elite(&[3]).map(|individual| individual.fitness),
+            Some(4.2)
+        );
+    }
+
+    #[test]
+    fn get_random() {
+        let mut elites_map = ElitesMap::new(4, vec![(-5.0, 5.0)]);
+
+        let individual = Individual {
+            behavior: vec![3.0],
+            fitness: 4.2,
+            ..Default::default()
+        };
+
+        elites_map.place_individual(individual);
+
+        let mut rng = ThreadRng::default();
+
+        assert!((elites_map.get_random_individual(&mut rng).fitness - 4.2).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn multiple_features() {
+        let mut elites_map = ElitesMap::new(4, vec![(-5.0, 5.0), (-5.0, 5.0)]);
+
+        let individual = Individual {
+            behavior: vec![3.0, -3.0],
+            fitness: 4.2,
+            ..Default::default()
+        };
+
+        elites_map.place_individual(individual);
+
         assert_eq!(
             elites_map
-                .map
-                .get(&vec![3])
+                .elite(&[3, 0])
                 .map(|individual| individual.fitness),
             Some(4.2)
         );
     }
 
     #[test]
-    fn get_random() {
+    fn handle_out_of_feature_range() {
+        let mut elites_map = ElitesMap::new(4, vec![(-5.0, -1.0), (1.0, 5.0)]);
+
+        let individual = Individual {
+            behavior: vec![-9.0, 9.0],
+            fitness: 3.9,
+            ..Default::default()
+        };
+
+        elites_map.place_individual(individual);
+
+        assert_eq!(
+            elites_map
+                .elite(&[0, 3])
+                .map(|individual| individual.fitness),
+            Some(3.9)
+        );
+        assert_eq!(elites_map.out_of_range_counts().clamped, 1);
+    }
+
+    #[test]
+    fn update_when_fitter() {
+        let mut elites_map = ElitesMap::new(4, vec![(-5.0, 5.0)]);
+
+        let individual_base = Individual {
+            behavior: vec![3.0],
+            fitness: 1.0,
+            ..Default::default()
+        };
+
+        let individual_less_fit = Individual {
+            behavior: vec![3.0],
+            fitness: 0.0,
+            ..Default::default()
+        };
+
+        let individual_more_fit = Individual {
+            behavior: vec![3.0],
+            fitness: 2.0,
+            ..Default::default()
+        };
+
+        elites_map.place_individual(individual_base);
+
+        assert_eq!(
+            elites_map.elite(&[3]).map(|individual| individual.fitness),
+            Some(1.0)
+        );
+
+        elites_map.place_individual(individual_less_fit);
+
+        assert_eq!(
+            elites_map.elite(&[3]).map(|individual| individual.fitness),
+            Some(1.0)
+        );
+
+        elites_map.place_individual(individual_more_fit);
+
+        assert_eq!(
+            elites_map.elite(&[3]).map(|individual| individual.fitness),
+            Some(2.0)
+        );
+    }
+
+    #[test]
+    fn update_resolution() {
+        let mut elites_map = ElitesMap::new(2, vec![(1.0, 2.0)]);
+
+        let individual = Individual {
+            behavior: vec![1.5],
+            fitness: 3.9,
+            ..Default::default()
+        };
+
+        elites_map.place_individual(individual);
+
+        assert_eq!(
+            elites_map.elite(&[0]).map(|individual| individual.fitness),
+            Some(3.9)
+        );
+
+        elites_map.update_resolution(3);
+
+        assert_eq!(
+            elites_map.elite(&[1]).map(|individual| individual.fitness),
+            Some(3.9)
+        );
+    }
+
+    #[test]
+    fn get_top_individual() {
+        let mut elites_map = ElitesMap::new(4, vec![(-5.0, 5.0)]);
+
+        let individual_less_fit = Individual {
+            behavior: vec![3.0],
+            fitness: 0.0,
+            ..Default::default()
+        };
+
+        let individual_more_fit = Individual {
+            behavior: vec![-3.0],
+            fitness: 2.0,
+            ..Default::default()
+        };
+
+        elites_map.place_individual(individual_less_fit);
+        elites_map.place_individual(individual_more_fit);
+
+        assert!((elites_map.top_individual().fitness - 2.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn get_capacity() {
+        let elites_map = ElitesMap::new(4, vec![(-5.0, 5.0), (-5.0, 5.0)]);
+
+        assert_eq!(elites_map.capacity(), 16);
+    }
+
+    #[test]
+    fn get_sorted_individuals() {
         let mut elites_map = ElitesMap::new(4, vec![(-5.0, 5.0)]);
 
-        let individual = Individual {
-            behavior: vec![3.0],
-            fitness: 4.2,
-            ..Default::default()
-        };
+        let individual_less_fit = Individual {
+            behavior: vec![3.0],
+            fitness: 1.0,
+            ..Default::default()
+        };
+
+        let individual_more_fit = Individual {
+            behavior: vec![-3.0],
+            fitness: 2.0,
+            ..Default::default()
+        };
+
+        elites_map.place_individual(individual_less_fit);
+        elites_map.place_individual(individual_more_fit);
+
+        let sorted_individuals = elites_map.sorted_individuals();
+
+        assert!((sorted_individuals[0].fitness - 2.0).abs() < f64::EPSILON);
+        assert!((sorted_individuals[1].fitness - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn get_neighbors() {
+        let mut elites_map = ElitesMap::new(3, vec![(0.0, 3.0)]);
+
+        let individual_up = Individual {
+            behavior: vec![3.0],
+            ..Default::default()
+        };
+
+        let individual_center = Individual {
+            behavior: vec![2.0],
+            ..Default::default()
+        };
+
+        let individual_down = Individual {
+            behavior: vec![1.0],
+            ..Default::default()
+        };
+
+        elites_map.place_individual(individual_up);
+        elites_map.place_individual(individual_center);
+        elites_map.place_individual(individual_down);
+
+        let position = vec![1usize];
+
+        let neighbors: Vec<&Individual> = elites_map.neighbors(&position).collect();
+
+        dbg!(neighbors.len());
+
+        assert_eq!(neighbors[0].behavior, vec![3.0]);
+        assert_eq!(neighbors[1].behavior, vec![1.0]);
+    }
+
+    #[test]
+    fn place_into_nearest_centroid() {
+        let mut elites_map = ElitesMap::with_centroids(
+            vec![vec![-2.5, -2.5], vec![2.5, 2.5], vec![-2.5, 2.5]],
+            vec![(-5.0, 5.0), (-5.0, 5.0)],
+        );
+
+        let individual = Individual {
+            behavior: vec![-1.0, 4.0],
+            fitness: 4.2,
+            ..Default::default()
+        };
+
+        elites_map.place_individual(individual);
+
+        assert_eq!(
+            elites_map.elite(&[2]).map(|individual| individual.fitness),
+            Some(4.2)
+        );
+        assert_eq!(elites_map.capacity(), 3);
+    }
+
+    #[test]
+    fn get_centroid_neighbors() {
+        let mut elites_map =
+            ElitesMap::with_centroids(vec![vec![0.5], vec![1.5], vec![2.5]], vec![(0.0, 3.0)]);
+
+        let individual_near = Individual {
+            behavior: vec![1.4],
+            ..Default::default()
+        };
+
+        let individual_far = Individual {
+            behavior: vec![2.6],
+            ..Default::default()
+        };
+
+        elites_map.place_individual(individual_near);
+        elites_map.place_individual(individual_far);
+
+        let position = vec![0usize];
+
+        let neighbors: Vec<&Individual> = elites_map.neighbors(&position).collect();
+
+        assert_eq!(neighbors.len(), 2);
+        assert_eq!(neighbors[0].behavior, vec![1.4]);
+        assert_eq!(neighbors[1].behavior, vec![2.6]);
+    }
+
+    #[test]
+    fn compute_centroids() {
+        let mut rng = ThreadRng::default();
+
+        let elites_map =
+            ElitesMap::centroidal_voronoi(10, vec![(-5.0, 5.0), (0.0, 1.0)], 1000, 10, &mut rng);
+
+        assert_eq!(elites_map.capacity(), 10);
+    }
+
+    #[test]
+    fn per_feature_resolution() {
+        let mut elites_map = ElitesMap::with_resolutions(vec![4, 2], vec![(-5.0, 5.0), (0.0, 1.0)]);
+
+        let individual = Individual {
+            behavior: vec![3.0, 0.7],
+            fitness: 4.2,
+            ..Default::default()
+        };
+
+        elites_map.place_individual(individual);
+
+        assert_eq!(elites_map.capacity(), 8);
+        assert_eq!(
+            elites_map
+                .elite(&[3, 1])
+                .map(|individual| individual.fitness),
+            Some(4.2)
+        );
+
+        elites_map.update_resolutions(vec![2, 1]);
+
+        assert_eq!(elites_map.capacity(), 2);
+        assert_eq!(
+            elites_map
+                .elite(&[1, 0])
+                .map(|individual| individual.fitness),
+            Some(4.2)
+        );
+    }
+
+    #[test]
+    fn get_neighbors_with_single_chunk_feature() {
+        let mut elites_map = ElitesMap::with_resolutions(vec![3, 1], vec![(0.0, 3.0), (0.0, 1.0)]);
+
+        let individual_up = Individual {
+            behavior: vec![2.5, 0.5],
+            ..Default::default()
+        };
+
+        let individual_center = Individual {
+            behavior: vec![1.5, 0.5],
+            ..Default::default()
+        };
+
+        elites_map.place_individual(individual_up);
+        elites_map.place_individual(individual_center);
+
+        let position = vec![1usize, 0];
+
+        let neighbors: Vec<&Individual> = elites_map.neighbors(&position).collect();
+
+        assert_eq!(neighbors.len(), 1);
+        assert_eq!(neighbors[0].behavior, vec![2.5, 0.5]);
+    }
+
+    #[test]
+    fn place_with_logarithmic_binning() {
+        let mut elites_map =
+            ElitesMap::with_binnings(vec![Binning::Logarithmic], vec![3], vec![(0.0, 63.0)]);
+
+        // chunk edges are at 3, 15 and 63
+        for &(value, fitness) in &[(2.0, 1.0), (4.0, 2.0), (14.0, 3.0), (63.0, 4.0)] {
+            elites_map.place_individual(Individual {
+                behavior: vec![value],
+                fitness,
+                ..Default::default()
+            });
+        }
+
+        assert_eq!(elites_map.elite(&[0]).map(|i| i.fitness), Some(1.0));
+        assert_eq!(elites_map.elite(&[1]).map(|i| i.fitness), Some(3.0));
+        assert_eq!(elites_map.elite(&[2]).map(|i| i.fitness), Some(4.0));
+    }
+
+    #[test]
+    fn place_with_explicit_edges() {
+        let mut elites_map = ElitesMap::with_binnings(
+            vec![Binning::Edges(vec![0.0, 1.0, 2.0, 4.0, 8.0])],
+            vec![16],
+            vec![(-5.0, 5.0)],
+        );
+
+        assert_eq!(elites_map.capacity(), 4);
+
+        for &(value, fitness) in &[(0.5, 1.0), (1.0, 2.0), (3.9, 3.0), (9.0, 4.0)] {
+            elites_map.place_individual(Individual {
+                behavior: vec![value],
+                fitness,
+                ..Default::default()
+            });
+        }
 
-        elites_map.place_individual(individual);
+        assert_eq!(elites_map.elite(&[0]).map(|i| i.fitness), Some(1.0));
+        assert_eq!(elites_map.elite(&[1]).map(|i| i.fitness), Some(2.0));
+        assert_eq!(elites_map.elite(&[2]).map(|i| i.fitness), Some(3.0));
+        assert_eq!(elites_map.elite(&[3]).map(|i| i.fitness), Some(4.0));
 
-        let mut rng = ThreadRng::default();
+        elites_map.update_resolution(2);
 
-        assert!((elites_map.get_random_individual(&mut rng).fitness - 4.2).abs() < f64::EPSILON);
+        assert_eq!(elites_map.capacity(), 4);
     }
 
     #[test]
-    fn multiple_features() {
-        let mut elites_map = ElitesMap::new(4, vec![(-5.0, 5.0), (-5.0, 5.0)]);
+    fn reject_out_of_feature_range() {
+        let mut elites_map = ElitesMap::new(4, vec![(-5.0, -1.0), (1.0, 5.0)])
+            .with_out_of_range_policy(OutOfRange::Reject);
 
         let individual = Individual {
-            behavior: vec![3.0, -3.0],
-            fitness: 4.2,
+            behavior: vec![-9.0, 3.0],
+            fitness: 3.9,
             ..Default::default()
         };
 
         elites_map.place_individual(individual);
 
+        assert_eq!(elites_map.len(), 0);
         assert_eq!(
-            elites_map
-                .map
-                .get(&vec![3, 0])
-                .map(|individual| individual.fitness),
-            Some(4.2)
+            elites_map.out_of_range_counts(),
+            OutOfRangeCounts {
+                rejected: 1,
+                ..Default::default()
+            }
         );
     }
 
     #[test]
-    fn handle_out_of_feature_range() {
-        let mut elites_map = ElitesMap::new(4, vec![(-5.0, -1.0), (1.0, 5.0)]);
+    fn place_behavior_at_wide_range_max() {
+        let mut elites_map =
+            ElitesMap::new(10, vec![(0.0, 100.0)]).with_storage(StorageBackend::Dense);
 
-        let individual = Individual {
-            behavior: vec![-9.0, 9.0],
-            fitness: 3.9,
+        elites_map.place_individual(Individual {
+            behavior: vec![100.0],
+            fitness: 1.0,
             ..Default::default()
-        };
-
-        elites_map.place_individual(individual);
+        });
 
         assert_eq!(
-            elites_map
-                .map
-                .get(&vec![0, 3])
-                .map(|individual| individual.fitness),
-            Some(3.9)
+            elites_map.elite(&[9]).map(|individual| individual.fitness),
+            Some(1.0)
         );
     }
 
     #[test]
-    fn update_when_fitter() {
-        let mut elites_map = ElitesMap::new(4, vec![(-5.0, 5.0)]);
+    fn grow_wide_range_to_behavior() {
+        let mut elites_map = ElitesMap::new(4, vec![(0.0, 20.0)])
+            .with_storage(StorageBackend::Dense)
+            .with_out_of_range_policy(OutOfRange::Grow);
 
-        let individual_base = Individual {
-            behavior: vec![3.0],
+        elites_map.place_individual(Individual {
+            behavior: vec![40.0],
             fitness: 1.0,
             ..Default::default()
-        };
+        });
 
-        let individual_less_fit = Individual {
-            behavior: vec![3.0],
-            fitness: 0.0,
+        assert_eq!(elites_map.feature_ranges(), &[(0.0, 40.0)]);
+        assert_eq!(
+            elites_map.elite(&[3]).map(|individual| individual.fitness),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn grow_out_of_feature_range() {
+        let mut elites_map =
+            ElitesMap::new(2, vec![(0.0, 2.0)]).with_out_of_range_policy(OutOfRange::Grow);
+
+        let individual_inside = Individual {
+            behavior: vec![1.5],
+            fitness: 1.0,
             ..Default::default()
         };
 
-        let individual_more_fit = Individual {
-            behavior: vec![3.0],
+        let individual_outside = Individual {
+            behavior: vec![4.0],
             fitness: 2.0,
             ..Default::default()
         };
 
-        elites_map.place_individual(individual_base);
+        elites_map.place_individual(individual_inside);
 
         assert_eq!(
-            elites_map
-                .map
-                .get(&vec![3])
-                .map(|individual| individual.fitness),
+            elites_map.elite(&[1]).map(|individual| individual.fitness),
             Some(1.0)
         );
 
-        elites_map.place_individual(individual_less_fit);
+        elites_map.place_individual(individual_outside);
 
+        assert_eq!(elites_map.feature_ranges(), &[(0.0, 4.0)]);
         assert_eq!(
-            elites_map
-                .map
-                .get(&vec![3])
-                .map(|individual| individual.fitness),
+            elites_map.elite(&[0]).map(|individual| individual.fitness),
             Some(1.0)
         );
-
-        elites_map.place_individual(individual_more_fit);
-
         assert_eq!(
-            elites_map
-                .map
-                .get(&vec![3])
-                .map(|individual| individual.fitness),
+            elites_map.elite(&[1]).map(|individual| individual.fitness),
             Some(2.0)
         );
+        assert_eq!(elites_map.out_of_range_counts().grown, 1);
     }
 
     #[test]
-    fn update_resolution() {
-        let mut elites_map = ElitesMap::new(2, vec![(1.0, 2.0)]);
+    fn slide_boundaries_to_observed_behaviors() {
+        let mut elites_map = ElitesMap::new(2, vec![(0.0, 100.0)])
+            .with_sliding_boundaries(SlidingBoundaries { remap_frequency: 5 });
+
+        for &(value, fitness) in &[(0.0, 1.0), (1.0, 2.0), (2.0, 3.0), (3.0, 4.0)] {
+            elites_map.place_individual(Individual {
+                behavior: vec![value],
+                fitness,
+                ..Default::default()
+            });
+        }
 
-        let individual = Individual {
-            behavior: vec![1.5],
-            fitness: 3.9,
+        // everything piles into the lower chunk before remapping
+        assert_eq!(elites_map.len(), 1);
+
+        elites_map.place_individual(Individual {
+            behavior: vec![4.0],
+            fitness: 0.0,
             ..Default::default()
-        };
+        });
 
-        elites_map.place_individual(individual);
+        assert_eq!(elites_map.feature_ranges(), &[(0.0, 4.0)]);
+        assert_eq!(
+            elites_map.elite(&[1]).map(|individual| individual.fitness),
+            Some(4.0)
+        );
+
+        elites_map.place_individual(Individual {
+            behavior: vec![0.5],
+            fitness: 0.0,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            elites_map.elite(&[0]).map(|individual| individual.fitness),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn slide_boundaries_to_rejected_behaviors() {
+        let mut elites_map = ElitesMap::new(2, vec![(0.0, 10.0)])
+            .with_out_of_range_policy(OutOfRange::Reject)
+            .with_sliding_boundaries(SlidingBoundaries { remap_frequency: 2 });
+
+        for &value in &[1.0, 2.0] {
+            elites_map.place_individual(Individual {
+                behavior: vec![value],
+                ..Default::default()
+            });
+        }
+
+        assert_eq!(elites_map.feature_ranges(), &[(1.0, 2.0)]);
+
+        for &value in &[5.0, 6.0] {
+            assert_eq!(
+                elites_map.place_individual(Individual {
+                    behavior: vec![value],
+                    ..Default::default()
+                }),
+                Insertion::Discarded { cell: None }
+            );
+        }
+
+        assert_eq!(elites_map.feature_ranges(), &[(1.0, 6.0)]);
+        assert_eq!(elites_map.out_of_range_counts().rejected, 2);
+    }
+
+    #[test]
+    fn update_resolution_before_observing_behaviors() {
+        let mut elites_map = ElitesMap::new(2, vec![(0.0, 100.0)])
+            .with_sliding_boundaries(SlidingBoundaries { remap_frequency: 5 });
+
+        elites_map.update_resolution(4);
+
+        assert_eq!(elites_map.feature_ranges(), &[(0.0, 100.0)]);
+        assert_eq!(elites_map.capacity(), 4);
+    }
+
+    #[test]
+    fn bound_observed_behaviors() {
+        let mut elites_map =
+            ElitesMap::new(2, vec![(0.0, 1.0)]).with_sliding_boundaries(SlidingBoundaries {
+                remap_frequency: 1000,
+            });
+
+        for index in 0..2 * OBSERVED_SAMPLE_SIZE {
+            elites_map.place_individual(Individual {
+                behavior: vec![index as f64],
+                ..Default::default()
+            });
+        }
+
+        let observed_behaviors = elites_map.observed_behaviors.as_ref().unwrap();
+        assert_eq!(observed_behaviors.observed, 2 * OBSERVED_SAMPLE_SIZE as u64);
+        assert_eq!(observed_behaviors.values[0].len(), OBSERVED_SAMPLE_SIZE);
+        // later behaviors made it into the sample as well
+        assert!(observed_behaviors.values[0]
+            .iter()
+            .any(|&value| value >= OBSERVED_SAMPLE_SIZE as f64));
+    }
+
+    #[test]
+    fn place_unstructured() {
+        let mut elites_map = ElitesMap::unstructured(1.0);
+
+        for &(behavior, fitness) in &[
+            ([0.0, 0.0], 1.0),
+            ([0.5, 0.5], 2.0),
+            ([3.0, 0.0], 1.0),
+            ([3.0, 0.5], 0.5),
+        ] {
+            elites_map.place_individual(Individual {
+                behavior: behavior.to_vec(),
+                fitness,
+                ..Default::default()
+            });
+        }
 
+        assert_eq!(elites_map.len(), 2);
         assert_eq!(
             elites_map
-                .map
-                .get(&vec![0])
-                .map(|individual| individual.fitness),
-            Some(3.9)
+                .elite(&[0])
+                .map(|individual| individual.behavior.clone()),
+            Some(vec![0.5, 0.5])
+        );
+        assert_eq!(
+            elites_map
+                .elite(&[1])
+                .map(|individual| individual.behavior.clone()),
+            Some(vec![3.0, 0.0])
         );
 
-        elites_map.update_resolution(3);
+        let position = vec![0usize];
 
+        let neighbors: Vec<&Individual> = elites_map.neighbors(&position).collect();
+
+        assert_eq!(neighbors.len(), 1);
+        assert_eq!(neighbors[0].behavior, vec![3.0, 0.0]);
+    }
+
+    #[test]
+    fn keep_fittest_per_cell() {
+        let mut elites_map = ElitesMap::new(2, vec![(0.0, 1.0)]).with_cell_capacity(CellCapacity {
+            size: 2,
+            replacement: Replacement::Worst,
+        });
+
+        for &fitness in &[1.0, 3.0, 2.0, 0.5] {
+            elites_map.place_individual(Individual {
+                behavior: vec![0.2],
+                fitness,
+                ..Default::default()
+            });
+        }
+
+        assert_eq!(elites_map.len(), 1);
         assert_eq!(
             elites_map
-                .map
-                .get(&vec![1])
-                .map(|individual| individual.fitness),
-            Some(3.9)
+                .sorted_individuals()
+                .iter()
+                .map(|individual| individual.fitness)
+                .collect::<Vec<f64>>(),
+            vec![3.0, 2.0]
         );
+        assert!((elites_map.top_individual().fitness - 3.0).abs() < f64::EPSILON);
+
+        let mut rng = ThreadRng::default();
+
+        assert!(elites_map.get_random_individual(&mut rng).fitness > 1.0);
     }
 
     #[test]
-    fn get_top_individual() {
+    fn replace_randomly_in_full_cell() {
+        let mut elites_map = ElitesMap::new(2, vec![(0.0, 1.0)]).with_cell_capacity(CellCapacity {
+            size: 3,
+            replacement: Replacement::Random,
+        });
+
+        for &fitness in &[1.0, 2.0, 3.0, 0.5] {
+            elites_map.place_individual(Individual {
+                behavior: vec![0.2],
+                fitness,
+                ..Default::default()
+            });
+        }
+
+        assert_eq!(elites_map.individuals().count(), 3);
+        assert!(elites_map
+            .individuals()
+            .any(|individual| (individual.fitness - 0.5).abs() < f64::EPSILON));
+    }
+
+    #[test]
+    fn seed_random_replacement() {
+        let replaced_fitness = |seed| {
+            let mut elites_map = ElitesMap::new(2, vec![(0.0, 1.0)])
+                .with_cell_capacity(CellCapacity {
+                    size: 3,
+                    replacement: Replacement::Random,
+                })
+                .with_seed(seed);
+
+            for &fitness in &[1.0, 2.0, 3.0, 0.5] {
+                elites_map.place_individual(Individual {
+                    behavior: vec![0.2],
+                    fitness,
+                    ..Default::default()
+                });
+            }
+
+            [1.0, 2.0, 3.0]
+                .iter()
+                .position(|&fitness| {
+                    elites_map
+                        .individuals()
+                        .all(|individual| (individual.fitness - fitness).abs() > f64::EPSILON)
+                })
+                .unwrap()
+        };
+
+        assert_eq!(replaced_fitness(3), replaced_fitness(3));
+        assert!((0..10).any(|seed| replaced_fitness(seed) != replaced_fitness(0)));
+    }
+
+    #[test]
+    fn report_competition_on_fitness() {
+        let mut elites_map = ElitesMap::new(2, vec![(0.0, 1.0)]).with_cell_capacity(CellCapacity {
+            size: 2,
+            replacement: Replacement::Worst,
+        });
+
+        for _ in 0..2 {
+            assert!(!elites_map.competes_on_fitness(&[0.2]));
+            elites_map.place_individual(Individual {
+                behavior: vec![0.2],
+                ..Default::default()
+            });
+        }
+
+        assert!(elites_map.competes_on_fitness(&[0.2]));
+        assert!(!elites_map.competes_on_fitness(&[0.7]));
+
+        // random replacement evicts regardless of fitness
+        let elites_map = elites_map.with_cell_capacity(CellCapacity {
+            size: 2,
+            replacement: Replacement::Random,
+        });
+        assert!(!elites_map.competes_on_fitness(&[0.2]));
+
+        let mut unstructured = ElitesMap::unstructured(0.5);
+        unstructured.place_individual(Individual {
+            behavior: vec![0.0],
+            ..Default::default()
+        });
+
+        assert!(unstructured.competes_on_fitness(&[0.3]));
+        assert!(!unstructured.competes_on_fitness(&[1.0]));
+    }
+
+    #[test]
+    fn use_as_archive() {
+        fn fill(archive: &mut impl Archive) {
+            for &(behavior, fitness) in &[(3.0, 1.0), (-3.0, 2.0), (-3.5, 0.5)] {
+                archive.place_individual(Individual {
+                    behavior: vec![behavior],
+                    fitness,
+                    ..Default::default()
+                });
+            }
+        }
+
         let mut elites_map = ElitesMap::new(4, vec![(-5.0, 5.0)]);
 
-        let individual_less_fit = Individual {
-            behavior: vec![3.0],
-            fitness: 0.0,
+        fill(&mut elites_map);
+
+        assert_eq!(Archive::len(&elites_map), 2);
+        assert_eq!(Archive::individuals(&elites_map).count(), 2);
+        assert!((Archive::top_individual(&elites_map).fitness - 2.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn report_insertion() {
+        let mut elites_map = ElitesMap::new(4, vec![(-5.0, 5.0)])
+            .with_cell_capacity(CellCapacity {
+                size: 2,
+                replacement: Replacement::Worst,
+            })
+            .with_out_of_range_policy(OutOfRange::Reject);
+
+        let individual = |behavior: f64, fitness: f64| Individual {
+            behavior: vec![behavior],
+            fitness,
             ..Default::default()
         };
 
-        let individual_more_fit = Individual {
-            behavior: vec![-3.0],
-            fitness: 2.0,
-            ..Default::default()
+        assert_eq!(
+            elites_map.place_individual(individual(3.0, 1.0)),
+            Insertion::NewCell { cell: vec![3] }
+        );
+        assert_eq!(
+            elites_map.place_individual(individual(3.0, 0.5)),
+            Insertion::Joined { cell: vec![3] }
+        );
+        assert_eq!(
+            elites_map.place_individual(individual(3.0, 0.0)),
+            Insertion::Discarded {
+                cell: Some(vec![3])
+            }
+        );
+        assert_eq!(
+            elites_map.place_individual(individual(3.0, 2.0)),
+            Insertion::Replaced {
+                cell: vec![3],
+                fitness_delta: 1.5
+            }
+        );
+        assert_eq!(
+            elites_map.place_individual(individual(6.0, 2.0)),
+            Insertion::Discarded { cell: None }
+        );
+    }
+
+    #[test]
+    fn dense_storage_matches_sparse() {
+        let mut sparse_map = ElitesMap::with_resolutions(vec![3, 4], vec![(0.0, 3.0), (0.0, 4.0)]);
+        let mut dense_map = ElitesMap::with_resolutions(vec![3, 4], vec![(0.0, 3.0), (0.0, 4.0)])
+            .with_storage(StorageBackend::Dense);
+
+        for (index, behavior) in [[0.5, 0.5], [1.5, 0.5], [0.5, 1.5], [1.5, 0.5], [2.5, 3.5]]
+            .iter()
+            .enumerate()
+        {
+            let individual = Individual {
+                behavior: behavior.to_vec(),
+                fitness: index as f64,
+                ..Default::default()
+            };
+            assert_eq!(
+                sparse_map.place_individual(individual.clone()),
+                dense_map.place_individual(individual)
+            );
+        }
+
+        assert_eq!(dense_map.len(), sparse_map.len());
+
+        let fitnesses = |elites_map: &ElitesMap| -> Vec<f64> {
+            elites_map
+                .sorted_individuals()
+                .iter()
+                .map(|individual| individual.fitness)
+                .collect()
         };
+        assert_eq!(fitnesses(&dense_map), fitnesses(&sparse_map));
 
-        elites_map.place_individual(individual_less_fit);
-        elites_map.place_individual(individual_more_fit);
+        let position = vec![0, 0];
+        let mut sparse_neighbors: Vec<f64> = sparse_map
+            .neighbors(&position)
+            .map(|individual| individual.fitness)
+            .collect();
+        let mut dense_neighbors: Vec<f64> = dense_map
+            .neighbors(&position)
+            .map(|individual| individual.fitness)
+            .collect();
+        sparse_neighbors.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        dense_neighbors.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(dense_neighbors, sparse_neighbors);
 
-        assert!((elites_map.top_individual().fitness - 2.0).abs() < f64::EPSILON);
+        dense_map.update_resolution(2);
+        sparse_map.update_resolution(2);
+
+        assert_eq!(dense_map.len(), sparse_map.len());
+        assert_eq!(fitnesses(&dense_map), fitnesses(&sparse_map));
     }
 
+    // run with `cargo test --release -- --ignored --nocapture`
     #[test]
-    fn get_capacity() {
-        let elites_map = ElitesMap::new(4, vec![(-5.0, 5.0), (-5.0, 5.0)]);
+    #[ignore]
+    fn benchmark_batch_selection() {
+        use rand::{rngs::SmallRng, SeedableRng};
+        use std::time::Instant;
+
+        let mut rng = SmallRng::seed_from_u64(42);
+        let mut elites_map = ElitesMap::new(64, vec![(0.0, 1.0), (0.0, 1.0)]);
+
+        for _ in 0..10_000 {
+            elites_map.place_individual(Individual {
+                behavior: vec![rng.gen(), rng.gen()],
+                fitness: rng.gen(),
+                ..Default::default()
+            });
+        }
 
-        assert_eq!(elites_map.capacity(), 16);
+        let batch_size = 200;
+
+        let start = Instant::now();
+        for _ in 0..batch_size {
+            elites_map.get_random_individual(&mut rng);
+        }
+        let per_draw = start.elapsed();
+
+        let start = Instant::now();
+        elites_map.get_random_individuals(batch_size, &mut rng);
+        let per_batch = start.elapsed();
+
+        println!(
+            "{} elites, {} parents: {:?} per draw, {:?} per batch",
+            elites_map.len(),
+            batch_size,
+            per_draw,
+            per_batch
+        );
+
+        assert!(per_batch < per_draw);
     }
 
     #[test]
-    fn get_sorted_individuals() {
+    fn track_curiosity_of_parents() {
+        let mut rng = ThreadRng::default();
         let mut elites_map = ElitesMap::new(4, vec![(-5.0, 5.0)]);
 
-        let individual_less_fit = Individual {
+        elites_map.place_individual(Individual {
             behavior: vec![3.0],
             fitness: 1.0,
             ..Default::default()
-        };
+        });
 
-        let individual_more_fit = Individual {
-            behavior: vec![-3.0],
-            fitness: 2.0,
-            ..Default::default()
-        };
+        let parent = elites_map.top_individual();
+        assert_eq!(elites_map.curiosity(&parent), Some(0.0));
 
-        elites_map.place_individual(individual_less_fit);
-        elites_map.place_individual(individual_more_fit);
+        let mut offspring = elites_map.get_random_individuals(2, &mut rng);
+        assert!(offspring
+            .iter()
+            .all(|child| child.parent == Some(parent.id)));
 
-        let sorted_individuals = elites_map.sorted_individuals();
+        // opens a new cell
+        offspring[0].behavior = vec![-3.0];
+        elites_map.place_individual(offspring.remove(0));
+        assert_eq!(elites_map.curiosity(&parent), Some(1.0));
 
-        assert!((sorted_individuals[0].fitness - 2.0).abs() < f64::EPSILON);
-        assert!((sorted_individuals[1].fitness - 1.0).abs() < f64::EPSILON);
+        // loses against its parent
+        offspring[0].fitness = 0.0;
+        elites_map.place_individual(offspring.remove(0));
+        assert_eq!(elites_map.curiosity(&parent), Some(0.0));
     }
 
     #[test]
-    fn get_neighbors() {
+    fn get_random_neighbor() {
+        let mut rng = ThreadRng::default();
         let mut elites_map = ElitesMap::new(3, vec![(0.0, 3.0)]);
 
-        let individual_up = Individual {
-            behavior: vec![3.0],
-            ..Default::default()
-        };
+        for &(behavior, fitness) in &[(0.5, 1.0), (2.5, 2.0)] {
+            elites_map.place_individual(Individual {
+                behavior: vec![behavior],
+                fitness,
+                ..Default::default()
+            });
+        }
 
-        let individual_center = Individual {
-            behavior: vec![2.0],
-            ..Default::default()
-        };
+        let parent = elites_map.get_random_individual(&mut rng);
 
-        let individual_down = Individual {
-            behavior: vec![1.0],
+        // the two occupied cells are not adjacent
+        assert!(elites_map.get_random_neighbor(&parent, &mut rng).is_none());
+
+        elites_map.place_individual(Individual {
+            behavior: vec![1.5],
+            fitness: 3.0,
             ..Default::default()
-        };
+        });
 
-        elites_map.place_individual(individual_up);
-        elites_map.place_individual(individual_center);
-        elites_map.place_individual(individual_down);
+        let neighbor = elites_map
+            .get_random_neighbor(&parent, &mut rng)
+            .expect("middle cell is occupied");
 
-        let position = vec![1usize];
+        assert_eq!(neighbor.behavior, vec![1.5]);
+        assert_eq!(neighbor.parent, Some(neighbor.id));
+    }
 
-        let neighbors: Vec<&Individual> = elites_map.neighbors(&position).collect();
+    #[test]
+    fn report_errors() {
+        let mut elites_map = ElitesMap::new(4, vec![(-5.0, 5.0)]);
+        let mut rng = ThreadRng::default();
 
-        dbg!(neighbors.len());
+        assert!(matches!(
+            elites_map.try_top_individual(),
+            Err(Error::EmptyArchive)
+        ));
+        assert!(matches!(
+            elites_map.try_get_random_individual(&mut rng),
+            Err(Error::EmptyArchive)
+        ));
+
+        let result = elites_map.try_place_individual(Individual {
+            behavior: vec![1.0, 2.0],
+            ..Default::default()
+        });
 
-        assert_eq!(neighbors[0].behavior, vec![3.0]);
-        assert_eq!(neighbors[1].behavior, vec![1.0]);
+        assert!(matches!(
+            result,
+            Err(Error::DescriptorDimensionMismatch {
+                expected: 1,
+                found: 2
+            })
+        ));
+        assert!(elites_map.is_empty());
     }
 }
@@ -0,0 +1,201 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use set_genome::GenomeContext;
+
+use crate::{
+    archive::Archive, individual::Individual, parameters::Crossover, selection::Selection,
+};
+
+/// A parent selection and variation policy producing offspring.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Emitter {
+    /// mutated copies of parents picked by the archive's selection, crossed over as configured by `crossover`
+    RandomElite,
+    /// parents crossed with an elite from a neighboring cell, or a random one if there is none, then mutated
+    NeighborCrossover,
+    /// mutated copies of parents whose offspring recently got into the archive
+    Improvement,
+}
+
+impl Emitter {
+    /// Returns the offspring and how many of them have two parents.
+    pub fn emit(
+        &self,
+        archive: &impl Archive,
+        count: usize,
+        crossover: &Crossover,
+        genome_context: &mut GenomeContext,
+    ) -> (Vec<Individual>, usize) {
+        if count == 0 {
+            return (Vec::new(), 0);
+        }
+
+        let parents = match self {
+            Emitter::Improvement => archive.get_random_individuals_with(
+                &Selection::Curiosity,
+                count,
+                &mut genome_context.rng,
+            ),
+            _ => archive.get_random_individuals(count, &mut genome_context.rng),
+        };
+
+        let crossing: Vec<bool> = match self {
+            Emitter::RandomElite => (0..count)
+                .map(|_| genome_context.rng.gen::<f64>() < crossover.probability)
+                .collect(),
+            Emitter::NeighborCrossover => vec![true; count],
+            Emitter::Improvement => vec![false; count],
+        };
+        let crossovers = crossing.iter().filter(|&&crossing| crossing).count();
+        let neighborhood = crossover.neighborhood || *self == Emitter::NeighborCrossover;
+
+        let mut mates = archive.get_random_individuals(crossovers, &mut genome_context.rng);
+
+        let offspring = parents
+            .into_iter()
+            .zip(crossing)
+            .map(|(parent, crossing)| {
+                let mut child = if crossing {
+                    let mate = mates.pop().expect("drew a mate for every crossover");
+                    let mate = if neighborhood {
+                        archive
+                            .get_random_neighbor(&parent, &mut genome_context.rng)
+                            .unwrap_or(mate)
+                    } else {
+                        mate
+                    };
+                    parent.crossover(&mate, &mut genome_context.rng)
+                } else {
+                    parent
+                };
+                child.mutate_with_context(genome_context);
+                child
+            })
+            .collect();
+
+        (offspring, crossovers)
+    }
+}
+
+/// Discounted UCB1 over the emitters' insertion success.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Bandit {
+    /// weight of the exploration term, `sqrt(2)` for plain UCB1
+    pub exploration: f64,
+    /// factor in ]0, 1] applied to past outcomes after every batch, lower values favor recent success
+    pub decay: f64,
+}
+
+impl Default for Bandit {
+    fn default() -> Self {
+        Self {
+            exploration: std::f64::consts::SQRT_2,
+            decay: 0.9,
+        }
+    }
+}
+
+/// Splits batches between emitters according to their recent success.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scheduler {
+    bandit: Bandit,
+    emitters: Vec<Emitter>,
+    // discounted number of offspring and successful offspring per emitter
+    offspring: Vec<f64>,
+    successes: Vec<f64>,
+}
+
+impl Scheduler {
+    pub fn new(emitters: Vec<Emitter>, bandit: Bandit) -> Self {
+        assert!(!emitters.is_empty(), "need at least one emitter");
+
+        Self {
+            bandit,
+            offspring: vec![0.0; emitters.len()],
+            successes: vec![0.0; emitters.len()],
+            emitters,
+        }
+    }
+
+    pub fn emitters(&self) -> &[Emitter] {
+        &self.emitters
+    }
+
+    /// Number of offspring per emitter, handing out one offspring at a time to the best UCB score.
+    pub fn allocate(&self, batch_size: usize) -> Vec<usize> {
+        let mut allocation = vec![0; self.emitters.len()];
+        let mut offspring = self.offspring.clone();
+
+        for _ in 0..batch_size {
+            let total: f64 = offspring.iter().sum();
+
+            let best = (0..self.emitters.len())
+                .map(|emitter| {
+                    if offspring[emitter] == 0.0 {
+                        return (emitter, f64::INFINITY);
+                    }
+                    // pending offspring count as trials with the mean observed so far
+                    let mean = if self.offspring[emitter] > 0.0 {
+                        self.successes[emitter] / self.offspring[emitter]
+                    } else {
+                        0.0
+                    };
+                    let exploration =
+                        self.bandit.exploration * (total.max(1.0).ln() / offspring[emitter]).sqrt();
+                    (emitter, mean + exploration)
+                })
+                .max_by(|(_, a), (_, b)| a.partial_cmp(b).expect("could not compare floats"))
+                .map(|(emitter, _)| emitter)
+                .expect("scheduler did not have emitters");
+
+            allocation[best] += 1;
+            offspring[best] += 1.0;
+        }
+
+        allocation
+    }
+
+    pub fn update(&mut self, emitter: usize, offspring: usize, successes: usize) {
+        self.offspring[emitter] += offspring as f64;
+        self.successes[emitter] += successes as f64;
+    }
+
+    /// Fades out past outcomes, to be called once per batch.
+    pub fn decay(&mut self) {
+        for (offspring, successes) in self.offspring.iter_mut().zip(self.successes.iter_mut()) {
+            *offspring *= self.bandit.decay;
+            *successes *= self.bandit.decay;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bandit, Emitter, Scheduler};
+
+    #[test]
+    fn favor_successful_emitters() {
+        let mut scheduler = Scheduler::new(
+            vec![Emitter::RandomElite, Emitter::Improvement],
+            Bandit::default(),
+        );
+
+        // unexplored emitters get offspring first
+        assert_eq!(scheduler.allocate(2), vec![1, 1]);
+
+        for _ in 0..10 {
+            let allocation = scheduler.allocate(100);
+            scheduler.update(0, allocation[0], allocation[0] / 2);
+            scheduler.update(1, allocation[1], allocation[1] / 10);
+            scheduler.decay();
+        }
+
+        let allocation = scheduler.allocate(100);
+
+        assert_eq!(allocation.iter().sum::<usize>(), 100);
+        assert!(allocation[0] > allocation[1]);
+        assert!(allocation[1] > 0);
+    }
+}
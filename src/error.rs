@@ -0,0 +1,63 @@
+use std::{fmt, io};
+
+use config::ConfigError;
+
+/// Everything that can go wrong within the crate.
+#[derive(Debug)]
+pub enum Error {
+    /// the config file could not be read
+    ConfigIo(ConfigError),
+    /// the config file content does not describe valid parameters
+    ConfigParse(ConfigError),
+    /// the parameters violate constraints, one message per problem
+    InvalidParameters(Vec<String>),
+    /// a behavior descriptor does not have one value per feature
+    DescriptorDimensionMismatch { expected: usize, found: usize },
+    /// an operation needed at least one stored individual
+    EmptyArchive,
+    /// the checkpoint file could not be read or written
+    CheckpointIo(io::Error),
+    /// the checkpoint file content does not describe a search state
+    CheckpointFormat(serde_json::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ConfigIo(error) => write!(f, "could not read config: {}", error),
+            Error::ConfigParse(error) => write!(f, "could not parse config: {}", error),
+            Error::InvalidParameters(problems) => {
+                write!(f, "invalid parameters: {}", problems.join("; "))
+            }
+            Error::DescriptorDimensionMismatch { expected, found } => write!(
+                f,
+                "behavior descriptor has {} values but there are {} features",
+                found, expected
+            ),
+            Error::EmptyArchive => write!(f, "archive did not hold any individual"),
+            Error::CheckpointIo(error) => write!(f, "could not access checkpoint: {}", error),
+            Error::CheckpointFormat(error) => write!(f, "could not process checkpoint: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ConfigIo(error) | Error::ConfigParse(error) => Some(error),
+            Error::CheckpointIo(error) => Some(error),
+            Error::CheckpointFormat(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(error: ConfigError) -> Self {
+        match error {
+            // file sources report missing or unreadable files as foreign errors
+            ConfigError::Foreign(_) => Error::ConfigIo(error),
+            _ => Error::ConfigParse(error),
+        }
+    }
+}
@@ -9,6 +9,23 @@ pub struct Individual {
     pub genome: Genome,
     pub behavior: Vec<f64>,
     pub fitness: f64,
+    /// assigned by the archive on placement
+    #[serde(default)]
+    pub id: u64,
+    /// id of the stored individual this one was derived from
+    #[serde(default)]
+    pub parent: Option<u64>,
+    /// drawn from the run's random state before every evaluation, for fitness functions that need randomness
+    #[serde(default)]
+    pub seed: u64,
+}
+
+/// First gene id above those of `individuals`, `None` if they have no genes.
+pub(crate) fn next_gene_id<'a>(individuals: impl Iterator<Item = &'a Individual>) -> Option<u64> {
+    individuals
+        .filter_map(Individual::max_gene_id)
+        .max()
+        .map(|id| id + 1)
 }
 
 impl Deref for Individual {
@@ -31,9 +48,17 @@ impl Individual {
             genome,
             behavior: Vec::new(),
             fitness: 0.0,
+            id: 0,
+            parent: None,
+            seed: 0,
         }
     }
 
+    /// Largest id among the genes of the genome, new genes need ids above it.
+    pub fn max_gene_id(&self) -> Option<u64> {
+        self.nodes().map(|node| node.id.0).max()
+    }
+
     // self is fitter if it has higher score or in case of equal score has fewer genes, i.e. less complexity
     pub fn is_fitter_than(&self, other: &Self) -> bool {
         self.fitness > other.fitness
@@ -52,6 +77,9 @@ impl Individual {
             genome: fitter.cross_in(weaker, rng),
             behavior: Vec::new(),
             fitness: 0.0,
+            id: 0,
+            parent: self.parent,
+            seed: 0,
         }
     }
 }
@@ -1,8 +1,36 @@
+mod archive;
+mod binning;
+mod builder;
+mod calibration;
+mod checkpoint;
+mod cvt;
 mod elites_map;
+mod emitter;
+mod error;
 mod individual;
 mod parameters;
 mod runtime;
+mod selection;
 mod statistics;
+mod storage;
 
+pub use crate::archive::{Archive, Insertion};
+pub use crate::binning::Binning;
+pub use crate::builder::RuntimeBuilder;
+pub use crate::calibration::Calibration;
+pub use crate::checkpoint::Checkpoint;
+pub use crate::elites_map::{
+    CellCapacity, ElitesMap, OutOfRange, OutOfRangeCounts, Replacement, SlidingBoundaries,
+};
+pub use crate::emitter::{Bandit, Emitter};
+pub use crate::error::Error;
 pub use crate::individual::Individual;
-pub use crate::runtime::Runtime;
+pub use crate::parameters::{
+    Crossover, EvaluationTimeout, FeatureRange, MapElitesParameters, Parameters, Tessellation,
+};
+pub use crate::runtime::{
+    FallibleFitnessFunction, FitnessFunction, Runtime, RuntimeIterator, WarmStart,
+};
+pub use crate::selection::{Candidates, Selection, Selector};
+pub use crate::statistics::{BatchReport, BatchStatistics, EmitterStatistics};
+pub use crate::storage::StorageBackend;
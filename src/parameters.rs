@@ -1,29 +1,424 @@
-use config::{Config, ConfigError, File};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+use config::{Config, File};
 use serde::{Deserialize, Serialize};
 use set_genome::Parameters as GenomeParameters;
 
-#[derive(Deserialize, Serialize, Default, Debug)]
+use crate::{
+    binning::Binning,
+    calibration::Calibration,
+    checkpoint::Checkpoint,
+    elites_map::{CellCapacity, OutOfRange, SlidingBoundaries},
+    emitter::{Bandit, Emitter},
+    error::Error,
+    selection::Selection,
+    storage::StorageBackend,
+};
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
 pub struct Parameters {
     pub map_elites: MapElitesParameters,
     pub genome: GenomeParameters,
 }
 
-#[derive(Deserialize, Serialize, Default, Debug)]
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
 pub struct MapElitesParameters {
     pub map_resolution: usize,
-    pub feature_ranges: Vec<(f64, f64)>,
+    pub feature_ranges: Vec<FeatureRange>,
     pub initial_runs: usize,
     pub batch_size: usize,
+    #[serde(default)]
+    pub tessellation: Tessellation,
+    #[serde(default)]
+    pub out_of_range: OutOfRange,
+    /// replaces `min` and `max` of configured feature ranges by percentiles of the initial population
+    #[serde(default)]
+    pub calibration: Option<Calibration>,
+    /// adapts grid chunks to the distribution of observed behaviors
+    #[serde(default)]
+    pub sliding_boundaries: Option<SlidingBoundaries>,
+    /// number of individuals per cell, one by default
+    #[serde(default)]
+    pub cell_capacity: CellCapacity,
+    /// `"sparse"` (default) or `"dense"`, dense storage is not available for unstructured archives
+    #[serde(default)]
+    pub storage: StorageBackend,
+    /// how the cells providing parents are picked, weighted by dominated neighbors by default
+    #[serde(default)]
+    pub selection: Selection,
+    /// offspring are mutated copies of a single parent unless configured otherwise
+    #[serde(default)]
+    pub crossover: Crossover,
+    /// variation policies sharing each batch, a single `random_elite` emitter by default
+    #[serde(default)]
+    pub emitters: Vec<Emitter>,
+    /// how batches are split between emitters
+    #[serde(default)]
+    pub bandit: Bandit,
+    /// treat panicking fitness functions like failing ones instead of aborting the batch
+    #[serde(default)]
+    pub catch_panics: bool,
+    /// wall-clock budget per evaluation, unlimited if not set
+    #[serde(default)]
+    pub evaluation_timeout: Option<EvaluationTimeout>,
+    /// makes runs reproducible unless evaluations time out, overrides the genome's seed
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// periodically save the search state to continue it with `Runtime::resume`
+    #[serde(default)]
+    pub checkpoint: Option<Checkpoint>,
+}
+
+/// Evaluations exceeding the budget are abandoned, their threads keep running until the fitness function returns.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct EvaluationTimeout {
+    pub milliseconds: u64,
+    /// place timed out offspring with this fitness and their parent's behavior where they have to beat a stored one, instead of dropping them
+    #[serde(default)]
+    pub penalty_fitness: Option<f64>,
+}
+
+/// Share of offspring produced by crossing two parents before mutation.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Crossover {
+    /// chance in [0, 1] that an offspring has two parents
+    pub probability: f64,
+    /// take the second parent from the cells neighboring the first one where possible
+    pub neighborhood: bool,
+}
+
+/// Either `[min, max]` or `{ min = .., max = .., resolution = .., scale = .. }`
+/// with `scale` being `"linear"` (default) or `"logarithmic"`,
+/// or `{ edges = [..] }` for explicit chunk boundaries.
+/// Features without own resolution use `map_resolution`.
+/// TOML does not allow mixing the array and table form within `feature_ranges`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(
+    try_from = "FeatureRangeRepresentation",
+    into = "FeatureRangeRepresentation"
+)]
+pub struct FeatureRange {
+    pub min: f64,
+    pub max: f64,
+    pub resolution: Option<usize>,
+    pub binning: Binning,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+enum FeatureRangeRepresentation {
+    Range(f64, f64),
+    Table {
+        min: Option<f64>,
+        max: Option<f64>,
+        resolution: Option<usize>,
+        #[serde(default)]
+        scale: Scale,
+        edges: Option<Vec<f64>>,
+    },
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+enum Scale {
+    #[default]
+    Linear,
+    Logarithmic,
+}
+
+impl TryFrom<FeatureRangeRepresentation> for FeatureRange {
+    type Error = String;
+
+    fn try_from(representation: FeatureRangeRepresentation) -> Result<Self, Self::Error> {
+        match representation {
+            FeatureRangeRepresentation::Range(min, max) => Ok(Self::from((min, max))),
+            FeatureRangeRepresentation::Table {
+                edges: Some(edges), ..
+            } => {
+                if edges.len() < 2 {
+                    return Err("feature range needs at least two edges".to_owned());
+                }
+                if edges.windows(2).any(|pair| pair[0] >= pair[1]) {
+                    return Err(format!(
+                        "feature range edges {:?} are not strictly increasing",
+                        edges
+                    ));
+                }
+                Ok(Self {
+                    min: edges[0],
+                    max: edges[edges.len() - 1],
+                    resolution: Some(edges.len() - 1),
+                    binning: Binning::Edges(edges),
+                })
+            }
+            FeatureRangeRepresentation::Table {
+                min: Some(min),
+                max: Some(max),
+                resolution,
+                scale,
+                edges: None,
+            } => Ok(Self {
+                min,
+                max,
+                resolution,
+                binning: match scale {
+                    Scale::Linear => Binning::Linear,
+                    Scale::Logarithmic => Binning::Logarithmic,
+                },
+            }),
+            _ => Err("feature range needs either min and max or edges".to_owned()),
+        }
+    }
+}
+
+/// Linearly binned range using `map_resolution`.
+impl From<(f64, f64)> for FeatureRange {
+    fn from((min, max): (f64, f64)) -> Self {
+        Self {
+            min,
+            max,
+            resolution: None,
+            binning: Binning::Linear,
+        }
+    }
+}
+
+impl From<FeatureRange> for FeatureRangeRepresentation {
+    fn from(feature_range: FeatureRange) -> Self {
+        let (scale, edges) = match feature_range.binning {
+            Binning::Linear => (Scale::Linear, None),
+            Binning::Logarithmic => (Scale::Logarithmic, None),
+            Binning::Edges(edges) => (Scale::Linear, Some(edges)),
+        };
+
+        FeatureRangeRepresentation::Table {
+            min: Some(feature_range.min),
+            max: Some(feature_range.max),
+            resolution: feature_range.resolution,
+            scale,
+            edges,
+        }
+    }
+}
+
+impl MapElitesParameters {
+    pub fn bounds(&self) -> Vec<(f64, f64)> {
+        self.feature_ranges
+            .iter()
+            .map(|feature_range| (feature_range.min, feature_range.max))
+            .collect()
+    }
+
+    pub fn binnings(&self) -> Vec<Binning> {
+        self.feature_ranges
+            .iter()
+            .map(|feature_range| feature_range.binning.clone())
+            .collect()
+    }
+
+    pub fn resolutions(&self) -> Vec<usize> {
+        self.feature_ranges
+            .iter()
+            .map(|feature_range| feature_range.resolution.unwrap_or(self.map_resolution))
+            .collect()
+    }
+    /// Collects every problem instead of stopping at the first one.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut problems = Vec::new();
+
+        if self.initial_runs == 0 {
+            problems.push("map_elites.initial_runs: has to be positive".to_owned());
+        }
+        if self.batch_size == 0 {
+            problems.push("map_elites.batch_size: has to be positive".to_owned());
+        }
+
+        match self.tessellation {
+            Tessellation::Grid => {
+                let uses_map_resolution = self
+                    .feature_ranges
+                    .iter()
+                    .any(|feature_range| feature_range.resolution.is_none());
+                if uses_map_resolution && self.map_resolution == 0 {
+                    problems.push("map_elites.map_resolution: has to be positive".to_owned());
+                }
+            }
+            Tessellation::CentroidalVoronoi {
+                centroids, samples, ..
+            } => {
+                if centroids == 0 {
+                    problems
+                        .push("map_elites.tessellation.centroids: has to be positive".to_owned());
+                }
+                if samples < centroids {
+                    problems.push(format!(
+                        "map_elites.tessellation.samples: {} samples can not place {} centroids",
+                        samples, centroids
+                    ));
+                }
+            }
+            Tessellation::Unstructured { threshold } => {
+                if threshold.is_nan() || threshold <= 0.0 {
+                    problems
+                        .push("map_elites.tessellation.threshold: has to be positive".to_owned());
+                }
+                if self.storage == StorageBackend::Dense {
+                    problems.push(
+                        "map_elites.storage: unstructured archives need sparse storage".to_owned(),
+                    );
+                }
+            }
+        }
+
+        // unstructured archives ignore feature ranges
+        if self.feature_ranges.is_empty()
+            && !matches!(self.tessellation, Tessellation::Unstructured { .. })
+        {
+            problems.push("map_elites.feature_ranges: needs at least one feature".to_owned());
+        }
+
+        for (index, feature_range) in self.feature_ranges.iter().enumerate() {
+            if feature_range.min.partial_cmp(&feature_range.max) != Some(Ordering::Less) {
+                problems.push(format!(
+                    "map_elites.feature_ranges[{}]: min {} is not below max {}",
+                    index, feature_range.min, feature_range.max
+                ));
+            }
+            if feature_range.resolution == Some(0) {
+                problems.push(format!(
+                    "map_elites.feature_ranges[{}].resolution: has to be positive",
+                    index
+                ));
+            }
+        }
+
+        if let Tessellation::Grid = self.tessellation {
+            let cells = self
+                .resolutions()
+                .iter()
+                .try_fold(1usize, |cells, &resolution| cells.checked_mul(resolution));
+            if cells.is_none() {
+                problems.push(format!(
+                    "map_elites.feature_ranges: {:?} chunks per feature exceed the addressable number of cells",
+                    self.resolutions()
+                ));
+            }
+        }
+
+        if self.cell_capacity.size == 0 {
+            problems.push("map_elites.cell_capacity.size: has to be positive".to_owned());
+        }
+
+        if let Some(calibration) = &self.calibration {
+            let in_range = |percentile: f64| (0.0..=100.0).contains(&percentile);
+            if !in_range(calibration.lower_percentile)
+                || !in_range(calibration.upper_percentile)
+                || calibration.lower_percentile >= calibration.upper_percentile
+            {
+                problems.push(format!(
+                    "map_elites.calibration: percentiles {} and {} are not increasing within [0, 100]",
+                    calibration.lower_percentile, calibration.upper_percentile
+                ));
+            }
+        }
+
+        if let Some(sliding_boundaries) = &self.sliding_boundaries {
+            if sliding_boundaries.remap_frequency == 0 {
+                problems.push(
+                    "map_elites.sliding_boundaries.remap_frequency: has to be positive".to_owned(),
+                );
+            }
+        }
+
+        if let Selection::Tournament { size: 0 } = self.selection {
+            problems.push("map_elites.selection.size: has to be positive".to_owned());
+        }
+
+        if !(0.0..=1.0).contains(&self.crossover.probability) {
+            problems.push(format!(
+                "map_elites.crossover.probability: {} is not within [0, 1]",
+                self.crossover.probability
+            ));
+        }
+
+        if !(self.bandit.decay > 0.0 && self.bandit.decay <= 1.0) {
+            problems.push(format!(
+                "map_elites.bandit.decay: {} is not within ]0, 1]",
+                self.bandit.decay
+            ));
+        }
+
+        if !(self.bandit.exploration.is_finite() && self.bandit.exploration >= 0.0) {
+            problems.push(format!(
+                "map_elites.bandit.exploration: {} is not a finite non-negative number",
+                self.bandit.exploration
+            ));
+        }
+
+        if let Some(checkpoint) = &self.checkpoint {
+            if checkpoint.path.is_empty() {
+                problems.push("map_elites.checkpoint.path: must not be empty".to_owned());
+            }
+            if checkpoint.frequency == 0 {
+                problems.push("map_elites.checkpoint.frequency: has to be positive".to_owned());
+            }
+        }
+
+        if let Some(evaluation_timeout) = &self.evaluation_timeout {
+            if evaluation_timeout.milliseconds == 0 {
+                problems.push(
+                    "map_elites.evaluation_timeout.milliseconds: has to be positive".to_owned(),
+                );
+            }
+            if let Some(penalty_fitness) = evaluation_timeout
+                .penalty_fitness
+                .filter(|penalty_fitness| !penalty_fitness.is_finite())
+            {
+                problems.push(format!(
+                    "map_elites.evaluation_timeout.penalty_fitness: {} is not finite",
+                    penalty_fitness
+                ));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidParameters(problems))
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Tessellation {
+    /// grid with `map_resolution` cells per feature unless a feature specifies its own
+    #[default]
+    Grid,
+    /// fixed number of cells computed by k-means over uniform samples of the feature ranges
+    CentroidalVoronoi {
+        centroids: usize,
+        samples: usize,
+        iterations: usize,
+    },
+    /// no predefined cells, behaviors closer than `threshold` compete, feature ranges are ignored
+    Unstructured { threshold: f64 },
 }
 
 impl Parameters {
-    pub fn new(path: &str) -> Result<Self, ConfigError> {
+    pub fn new(path: &str) -> Result<Self, Error> {
         let mut s = Config::new();
 
         // Start off by merging in the "default" configuration file
         s.merge(File::with_name(path))?;
 
         // You can deserialize (and thus freeze) the entire configuration as
-        s.try_into()
+        let parameters: Self = s.try_into()?;
+
+        parameters.map_elites.validate()?;
+
+        Ok(parameters)
     }
 }
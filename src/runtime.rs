@@ -1,29 +1,96 @@
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
-use set_genome::GenomeContext;
-use tracing::{debug, info};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{
+    mpsc::{self, RecvTimeoutError},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::Duration;
 
-use crate::{elites_map::ElitesMap, parameters::Parameters, Individual};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use set_genome::{GenomeContext, IdGenerator};
+use tracing::{debug, info, warn};
+
+use crate::{
+    archive::{Archive, Insertion},
+    builder::RuntimeBuilder,
+    checkpoint::{GenomeState, SearchState, SearchStateRef},
+    cvt,
+    elites_map::ElitesMap,
+    emitter::{Emitter, Scheduler},
+    error::Error,
+    individual::next_gene_id,
+    parameters::{Parameters, Tessellation},
+    statistics::{BatchReport, BatchStatistics, EmitterStatistics},
+    Individual,
+};
+
+/// Evaluates an individual to its fitness and behavior descriptor.
+pub type FitnessFunction = Box<dyn Fn(&Individual) -> (f64, Vec<f64>) + Send + Sync>;
+
+/// Evaluates an individual or reports why it could not, failed individuals are dropped.
+pub type FallibleFitnessFunction = Box<
+    dyn Fn(&Individual) -> Result<(f64, Vec<f64>), Box<dyn std::error::Error + Send + Sync>>
+        + Send
+        + Sync,
+>;
 
 pub struct Runtime {
-    fitness_function: Box<dyn Fn(&Individual) -> (f64, Vec<f64>) + Send + Sync>,
+    // shared with the threads of evaluations that can time out
+    fitness_function: Arc<FallibleFitnessFunction>,
     pub parameters: Parameters,
+    // k-means is expensive, every map built with the same tessellation and feature ranges shares its cells
+    centroids: Mutex<Option<Centroids>>,
+}
+
+struct Centroids {
+    tessellation: Tessellation,
+    feature_ranges: Vec<(f64, f64)>,
+    centroids: Vec<Vec<f64>>,
 }
 
-pub struct RuntimeIterator<'a> {
+/// How `Runtime::initilize_from_archive` treats the saved map.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WarmStart {
+    /// place the saved individuals into a map built from the current parameters instead of the saved cells
+    pub rebin: bool,
+    /// evaluate the saved individuals again, e.g. after the fitness function changed
+    pub reevaluate: bool,
+}
+
+pub struct RuntimeIterator<'a, A: Archive = ElitesMap> {
     runtime: &'a Runtime,
-    elites_map: ElitesMap,
+    archive: A,
     genome_context: GenomeContext,
+    scheduler: Scheduler,
+    batch_statistics: BatchStatistics,
+    batches: usize,
 }
 
 impl Runtime {
-    pub fn new(
+    /// Loads the parameters from the config file at `path`.
+    pub fn new(path: &str, fitness_function: FitnessFunction) -> Result<Self, Error> {
+        RuntimeBuilder::from_path(path, fitness_function)?.build()
+    }
+
+    /// Loads the parameters from the config file at `path`.
+    pub fn new_fallible(
         path: &str,
-        fitness_function: Box<dyn Fn(&Individual) -> (f64, Vec<f64>) + Send + Sync>,
+        fitness_function: FallibleFitnessFunction,
+    ) -> Result<Self, Error> {
+        RuntimeBuilder::new_fallible(fitness_function)
+            .load(path)?
+            .build()
+    }
+
+    pub(crate) fn with_parameters(
+        parameters: Parameters,
+        fitness_function: FallibleFitnessFunction,
     ) -> Self {
-        let parameters = Parameters::new(path).unwrap();
         Self {
             parameters,
-            fitness_function,
+            fitness_function: Arc::new(fitness_function),
+            centroids: Mutex::new(None),
         }
     }
 
@@ -33,86 +100,1037 @@ impl Runtime {
         individual.behavior = behavior;
     } */
 
-    fn evaluate_parallel(&self, individuals: &mut Vec<Individual>) {
+    /// Keeps the order of `individuals`.
+    fn evaluate_parallel(
+        &self,
+        mut individuals: Vec<Individual>,
+        rng: &mut impl Rng,
+    ) -> Vec<Evaluation> {
         info!("evaluating {} individuals in parallel", individuals.len());
 
+        // drawn up front so the seeds do not depend on how rayon schedules the evaluations
+        for individual in &mut individuals {
+            individual.seed = rng.gen();
+        }
+
         individuals
-            .par_iter_mut()
+            .into_par_iter()
             .enumerate()
-            .map(|(index, individual)| {
-                let (fitness, behavior) = (self.fitness_function)(individual);
+            .map(|(index, individual)| self.evaluate(index, individual))
+            .collect()
+    }
+
+    fn evaluate(&self, index: usize, mut individual: Individual) -> Evaluation {
+        let catch_panics = self.parameters.map_elites.catch_panics;
+
+        let evaluation = match &self.parameters.map_elites.evaluation_timeout {
+            None => call_fitness_function(&self.fitness_function, &individual, catch_panics),
+            Some(timeout) => {
+                // a stuck evaluation can not be stopped, it occupies its own thread until it returns
+                let (sender, receiver) = mpsc::channel();
+                let fitness_function = Arc::clone(&self.fitness_function);
+                let candidate = individual.clone();
+
+                thread::spawn(move || {
+                    // the receiver is gone if the evaluation timed out
+                    let _ = sender.send(call_fitness_function(
+                        &fitness_function,
+                        &candidate,
+                        catch_panics,
+                    ));
+                });
+
+                match receiver.recv_timeout(Duration::from_millis(timeout.milliseconds)) {
+                    Ok(evaluation) => evaluation,
+                    Err(RecvTimeoutError::Timeout) => {
+                        warn!(
+                            "evaluation of {}th individual timed out after {}ms",
+                            index, timeout.milliseconds
+                        );
+                        return match timeout.penalty_fitness {
+                            // the behavior is still the one inherited from the parent
+                            Some(penalty_fitness) if !individual.behavior.is_empty() => {
+                                individual.fitness = penalty_fitness;
+                                Evaluation::TimedOut(Some(individual))
+                            }
+                            _ => Evaluation::TimedOut(None),
+                        };
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        panic!("evaluation of {}th individual panicked", index)
+                    }
+                }
+            }
+        };
+
+        match evaluation {
+            Ok((fitness, behavior)) => {
                 individual.fitness = fitness;
                 individual.behavior = behavior;
                 debug!("evaluated {}th individual", index);
-            })
-            .collect::<()>()
+                Evaluation::Evaluated(individual)
+            }
+            Err(error) => {
+                warn!("evaluation of {}th individual failed: {}", index, error);
+                Evaluation::Failed
+            }
+        }
+    }
+
+    /// Runs the search on an `ElitesMap` as configured by the parameters.
+    pub fn initilize(&self) -> RuntimeIterator<'_> {
+        self.initilize_with_individuals(Vec::new())
     }
 
-    pub fn initilize(&self) -> RuntimeIterator {
+    /// Evaluates and places `seeds` before random individuals fill up the remaining `initial_runs`.
+    /// The seeds' genomes have to stem from the same genome parameters, new genes get ids above theirs.
+    pub fn initilize_with_individuals(&self, seeds: Vec<Individual>) -> RuntimeIterator<'_> {
         info!("starting runtime initialization");
 
-        let mut genome_context = GenomeContext::new(self.parameters.genome.clone());
+        let mut genome_context = self.genome_context_after(seeds.iter());
 
-        // generate individual with initial ids for genome
-        let initial_individual = Individual::from_genome(genome_context.uninitialized_genome());
+        let (initial_individuals, mut statistics) =
+            self.initial_individuals(seeds, &mut genome_context);
 
-        let mut elites_map = ElitesMap::new(
-            self.parameters.map_elites.map_resolution,
-            self.parameters.map_elites.feature_ranges.clone(),
-        );
+        let mut elites_map = self.elites_map(&initial_individuals, &mut genome_context);
 
-        let mut initial_individuals: Vec<Individual> = (0..self.parameters.map_elites.initial_runs)
-            .map(|_| {
-                let mut other_individual = initial_individual.clone();
-                other_individual.init_with_context(&mut genome_context);
-                other_individual.mutate_with_context(&mut genome_context);
-                other_individual
-            })
-            .collect();
+        place_all(&mut elites_map, initial_individuals, &mut statistics);
 
-        self.evaluate_parallel(&mut initial_individuals);
+        self.iterate(elites_map, genome_context, statistics)
+    }
+
+    /// Continues the search on a previously saved map, its individuals lose their ancestry
+    /// and new genes get ids above theirs.
+    pub fn initilize_from_archive(
+        &self,
+        mut elites_map: ElitesMap,
+        warm_start: WarmStart,
+    ) -> RuntimeIterator<'_> {
+        info!("starting runtime from saved archive");
+
+        let mut genome_context = self.genome_context_after(elites_map.individuals());
+
+        if !warm_start.rebin && !warm_start.reevaluate {
+            return self.iterate(elites_map, genome_context, BatchStatistics::default());
+        }
 
-        for individual in initial_individuals {
-            elites_map.place_individual(individual);
+        let mut individuals = elites_map.take_individuals();
+        for individual in &mut individuals {
+            individual.parent = None;
         }
 
+        let mut statistics = BatchStatistics::default();
+
+        if warm_start.reevaluate {
+            individuals = self
+                .evaluate_parallel(individuals, &mut genome_context.rng)
+                .into_iter()
+                // the map gets refilled, penalized individuals would only take free places
+                .filter_map(|evaluation| {
+                    let penalized = evaluation.is_penalized();
+                    evaluation.record(&mut statistics).filter(|_| !penalized)
+                })
+                .collect();
+
+            if statistics.failed_evaluations + statistics.timed_out_evaluations > 0 {
+                warn!(
+                    "dropped {} failed and {} timed out evaluations of saved individuals",
+                    statistics.failed_evaluations, statistics.timed_out_evaluations
+                );
+            }
+        }
+
+        if warm_start.rebin {
+            elites_map = self.elites_map(&individuals, &mut genome_context);
+        }
+
+        place_all(&mut elites_map, individuals, &mut statistics);
+
+        self.iterate(elites_map, genome_context, statistics)
+    }
+
+    /// Runs the search on any archive, the archive related parameters are not used.
+    pub fn initilize_with_archive<A: Archive>(&self, mut archive: A) -> RuntimeIterator<'_, A> {
+        info!("starting runtime initialization");
+
+        let mut genome_context = self.genome_context();
+
+        let (initial_individuals, mut statistics) =
+            self.initial_individuals(Vec::new(), &mut genome_context);
+
+        place_all(&mut archive, initial_individuals, &mut statistics);
+
+        self.iterate(archive, genome_context, statistics)
+    }
+
+    /// Continues the search saved in a checkpoint, the parameters have to match those of the saved run.
+    pub fn resume(&self, path: &str) -> Result<RuntimeIterator<'_>, Error> {
+        info!("resuming from checkpoint {}", path);
+
+        let state: SearchState<ElitesMap> = SearchState::read(path)?;
+
+        Ok(RuntimeIterator {
+            genome_context: state.genome_state.restore(&self.parameters.genome),
+            archive: state.archive,
+            runtime: self,
+            scheduler: state.scheduler,
+            batch_statistics: BatchStatistics::default(),
+            batches: state.batches,
+        })
+    }
+
+    /// An empty map as configured by the parameters, calibrated on `calibration_sample` if requested.
+    fn elites_map(
+        &self,
+        calibration_sample: &[Individual],
+        genome_context: &mut GenomeContext,
+    ) -> ElitesMap {
+        let feature_ranges = match &self.parameters.map_elites.calibration {
+            Some(calibration) => {
+                calibration.feature_ranges(calibration_sample, &self.parameters.map_elites.bounds())
+            }
+            None => self.parameters.map_elites.bounds(),
+        };
+
+        let mut elites_map = match self.parameters.map_elites.tessellation {
+            Tessellation::Grid => ElitesMap::with_binnings(
+                self.parameters.map_elites.binnings(),
+                self.parameters.map_elites.resolutions(),
+                feature_ranges,
+            ),
+            Tessellation::CentroidalVoronoi { .. } => {
+                ElitesMap::with_centroids(self.centroids(&feature_ranges), feature_ranges)
+            }
+            Tessellation::Unstructured { threshold } => ElitesMap::unstructured(threshold),
+        }
+        .with_storage(self.parameters.map_elites.storage)
+        .with_seed(genome_context.rng.gen())
+        .with_selection(self.parameters.map_elites.selection)
+        .with_cell_capacity(self.parameters.map_elites.cell_capacity)
+        .with_out_of_range_policy(self.parameters.map_elites.out_of_range);
+
+        if let Some(sliding_boundaries) = &self.parameters.map_elites.sliding_boundaries {
+            elites_map = elites_map.with_sliding_boundaries(sliding_boundaries.clone());
+        }
+
+        if self.parameters.map_elites.calibration.is_some() {
+            info!(
+                "calibrated feature ranges: {:?}",
+                elites_map.feature_ranges()
+            );
+        }
+
+        elites_map
+    }
+
+    /// Computed on first use and whenever the tessellation or the feature ranges changed.
+    /// The samples do not draw from the genome context, so runs stay the same whether the centroids were cached or not.
+    fn centroids(&self, feature_ranges: &[(f64, f64)]) -> Vec<Vec<f64>> {
+        let tessellation = &self.parameters.map_elites.tessellation;
+        let mut cached = self.centroids.lock().expect("centroid cache was poisoned");
+
+        if let Some(cached) = cached.as_ref().filter(|cached| {
+            &cached.tessellation == tessellation && cached.feature_ranges == feature_ranges
+        }) {
+            return cached.centroids.clone();
+        }
+
+        let (number_of_centroids, samples, iterations) = match *tessellation {
+            Tessellation::CentroidalVoronoi {
+                centroids,
+                samples,
+                iterations,
+            } => (centroids, samples, iterations),
+            _ => unreachable!("centroids are only needed for centroidal voronoi tessellations"),
+        };
+
+        info!("computing {} centroids", number_of_centroids);
+
+        let centroids = cvt::centroids(
+            number_of_centroids,
+            feature_ranges,
+            samples,
+            iterations,
+            &mut SmallRng::seed_from_u64(self.parameters.map_elites.seed.unwrap_or_default()),
+        );
+
+        *cached = Some(Centroids {
+            tessellation: tessellation.clone(),
+            feature_ranges: feature_ranges.to_vec(),
+            centroids: centroids.clone(),
+        });
+
+        centroids
+    }
+
+    /// `statistics` describe the initialization in place of a previous batch.
+    fn iterate<A: Archive>(
+        &self,
+        archive: A,
+        genome_context: GenomeContext,
+        statistics: BatchStatistics,
+    ) -> RuntimeIterator<'_, A> {
         RuntimeIterator {
             genome_context,
-            elites_map,
+            archive,
             runtime: self,
+            scheduler: self.scheduler(),
+            batch_statistics: statistics,
+            batches: 0,
+        }
+    }
+
+    /// Seeded by `seed` if set, which drives every random decision of a run.
+    fn genome_context(&self) -> GenomeContext {
+        let mut genome_parameters = self.parameters.genome.clone();
+
+        if let Some(seed) = self.parameters.map_elites.seed {
+            genome_parameters.seed = Some(seed);
+        }
+
+        GenomeContext::new(genome_parameters)
+    }
+
+    /// Hands out gene ids above those of `individuals`, which stem from another context.
+    fn genome_context_after<'a>(
+        &self,
+        individuals: impl Iterator<Item = &'a Individual>,
+    ) -> GenomeContext {
+        let mut genome_context = self.genome_context();
+
+        if let Some(next_gene_id) = next_gene_id(individuals) {
+            genome_context.id_gen = IdGenerator::new(next_gene_id);
+        }
+
+        genome_context
+    }
+
+    fn scheduler(&self) -> Scheduler {
+        let mut emitters = self.parameters.map_elites.emitters.clone();
+
+        if emitters.is_empty() {
+            emitters.push(Emitter::RandomElite);
+        }
+
+        Scheduler::new(emitters, self.parameters.map_elites.bandit.clone())
+    }
+
+    /// Evaluates the seeds and as many random individuals as needed to reach `initial_runs`.
+    fn initial_individuals(
+        &self,
+        seeds: Vec<Individual>,
+        genome_context: &mut GenomeContext,
+    ) -> (Vec<Individual>, BatchStatistics) {
+        // generate individual with initial ids for genome
+        let initial_individual = Individual::from_genome(genome_context.uninitialized_genome());
+
+        let random_runs = self
+            .parameters
+            .map_elites
+            .initial_runs
+            .saturating_sub(seeds.len());
+
+        let mut initial_individuals = seeds;
+        initial_individuals.extend((0..random_runs).map(|_| {
+            let mut other_individual = initial_individual.clone();
+            other_individual.init_with_context(genome_context);
+            other_individual.mutate_with_context(genome_context);
+            other_individual
+        }));
+
+        let mut statistics = BatchStatistics::default();
+
+        let evaluated_individuals: Vec<Individual> = self
+            .evaluate_parallel(initial_individuals, &mut genome_context.rng)
+            .into_iter()
+            .filter_map(|evaluation| evaluation.record(&mut statistics))
+            .collect();
+
+        if statistics.failed_evaluations + statistics.timed_out_evaluations > 0 {
+            warn!(
+                "dropped {} failed and {} timed out initial evaluations",
+                statistics.failed_evaluations, statistics.timed_out_evaluations
+            );
+        }
+
+        (evaluated_individuals, statistics)
+    }
+}
+
+impl<A: Archive> RuntimeIterator<'_, A> {
+    /// Insertion outcomes of the most recent batch, or of the initial population before the first batch.
+    pub fn batch_statistics(&self) -> &BatchStatistics {
+        &self.batch_statistics
+    }
+
+    /// The live archive, valid until the next batch.
+    pub fn archive(&self) -> &A {
+        &self.archive
+    }
+
+    /// A copy of the archive, copies every stored genome.
+    pub fn snapshot(&self) -> A {
+        self.archive.clone()
+    }
+
+    pub fn into_archive(self) -> A {
+        self.archive
+    }
+
+    /// Number of batches since initialization, including those before resuming.
+    pub fn batches(&self) -> usize {
+        self.batches
+    }
+
+    /// Saves the search state for `Runtime::resume`, the genome context gets reseeded to match the saved state.
+    pub fn checkpoint(&mut self, path: &str) -> Result<(), Error> {
+        let genome_state =
+            GenomeState::capture(&mut self.genome_context, self.archive.individuals());
+        self.genome_context = genome_state.restore(&self.runtime.parameters.genome);
+
+        SearchStateRef {
+            batches: self.batches,
+            archive: &self.archive,
+            genome_state,
+            scheduler: &self.scheduler,
         }
+        .write(path)
     }
 }
 
-impl<'a> Iterator for RuntimeIterator<'a> {
-    type Item = ElitesMap;
+/// Runs one batch per item, the archive is accessed through the iterator in between.
+impl<'a, A: Archive> Iterator for RuntimeIterator<'a, A> {
+    type Item = BatchReport;
 
     fn next(&mut self) -> Option<Self::Item> {
+        // every initial individual can fail its evaluation or be rejected by the archive
+        if self.archive.is_empty() {
+            warn!(
+                "stopping search on empty archive, {} failed and {} timed out evaluations, {} individuals discarded",
+                self.batch_statistics.failed_evaluations,
+                self.batch_statistics.timed_out_evaluations,
+                self.batch_statistics.discarded
+            );
+            return None;
+        }
+
         info!("selecting next individual batch");
 
-        let mut random_individuals: Vec<Individual> =
-            (0..self.runtime.parameters.map_elites.batch_size)
-                .map(|_| {
-                    let mut random_individual = self
-                        .elites_map
-                        .get_random_individual(&mut self.genome_context.rng);
-                    random_individual.mutate_with_context(&mut self.genome_context);
-                    random_individual
-                })
-                .collect();
+        let batch_size = self.runtime.parameters.map_elites.batch_size;
+        let crossover = &self.runtime.parameters.map_elites.crossover;
+
+        let allocation = self.scheduler.allocate(batch_size);
+
+        let mut batch_statistics = BatchStatistics {
+            emitters: vec![EmitterStatistics::default(); allocation.len()],
+            ..Default::default()
+        };
+
+        let mut random_individuals = Vec::with_capacity(batch_size);
+        // index of the emitter for every individual
+        let mut origins = Vec::with_capacity(batch_size);
+
+        for (emitter_index, (emitter, &count)) in self
+            .scheduler
+            .emitters()
+            .iter()
+            .zip(allocation.iter())
+            .enumerate()
+        {
+            debug!("emitting {} individuals with {:?}", count, emitter);
+
+            let (offspring, crossovers) =
+                emitter.emit(&self.archive, count, crossover, &mut self.genome_context);
+
+            batch_statistics.crossovers += crossovers;
+            random_individuals.extend(offspring);
+            origins.extend(std::iter::repeat_n(emitter_index, count));
+        }
 
         info!("evaluating selected individual batch");
 
-        self.runtime.evaluate_parallel(&mut random_individuals);
+        let evaluated_individuals = self
+            .runtime
+            .evaluate_parallel(random_individuals, &mut self.genome_context.rng);
 
         info!("placing evaluated individual batch");
 
-        for individual in random_individuals {
-            self.elites_map.place_individual(individual);
+        for (evaluation, emitter_index) in evaluated_individuals.into_iter().zip(origins) {
+            let emitter_statistics = &mut batch_statistics.emitters[emitter_index];
+            emitter_statistics.offspring += 1;
+
+            let penalized = evaluation.is_penalized();
+            let individual = match evaluation.record(&mut batch_statistics) {
+                Some(individual) => individual,
+                None => continue,
+            };
+
+            // an unevaluated genome may only get stored by its penalty beating an evaluated one
+            if penalized && !self.archive.competes_on_fitness(&individual.behavior) {
+                batch_statistics.record(&Insertion::Discarded { cell: None });
+                continue;
+            }
+
+            let insertion = match try_place(&mut self.archive, individual, &mut batch_statistics) {
+                Some(insertion) => insertion,
+                None => continue,
+            };
+            if insertion.is_success() {
+                batch_statistics.emitters[emitter_index].successes += 1;
+            }
+        }
+
+        self.scheduler.decay();
+        for (emitter_index, emitter_statistics) in batch_statistics.emitters.iter().enumerate() {
+            self.scheduler.update(
+                emitter_index,
+                emitter_statistics.offspring,
+                emitter_statistics.successes,
+            );
+        }
+
+        info!(
+            "finished batch: {} new cells, {} joined, {} replaced, {} discarded, {} crossovers, {} failed and {} timed out evaluations",
+            batch_statistics.new_cells,
+            batch_statistics.joined,
+            batch_statistics.replaced,
+            batch_statistics.discarded,
+            batch_statistics.crossovers,
+            batch_statistics.failed_evaluations,
+            batch_statistics.timed_out_evaluations
+        );
+
+        self.batches += 1;
+
+        let report = BatchReport {
+            batch: self.batches,
+            occupied_cells: self.archive.len(),
+            best_fitness: self
+                .archive
+                .individuals()
+                .map(|individual| individual.fitness)
+                .fold(f64::NEG_INFINITY, f64::max),
+            statistics: batch_statistics.clone(),
+        };
+
+        self.batch_statistics = batch_statistics;
+
+        let runtime = self.runtime;
+        if let Some(checkpoint) = &runtime.parameters.map_elites.checkpoint {
+            if self.batches.is_multiple_of(checkpoint.frequency) {
+                info!("writing checkpoint after batch {}", self.batches);
+                // losing a checkpoint is no reason to abort the search
+                if let Err(error) = self.checkpoint(&checkpoint.path) {
+                    warn!("could not write checkpoint: {}", error);
+                }
+            }
+        }
+
+        Some(report)
+    }
+}
+
+enum Evaluation {
+    Evaluated(Individual),
+    Failed,
+    /// holds the individual if it is to be placed with the penalty fitness
+    TimedOut(Option<Individual>),
+}
+
+impl Evaluation {
+    /// Timed out but to be placed with the penalty fitness.
+    fn is_penalized(&self) -> bool {
+        matches!(self, Evaluation::TimedOut(Some(_)))
+    }
+
+    /// Counts the evaluation as offspring and whether it failed or timed out, returns the individual to place if there is one.
+    fn record(self, statistics: &mut BatchStatistics) -> Option<Individual> {
+        statistics.offspring += 1;
+        match self {
+            Evaluation::Evaluated(individual) => Some(individual),
+            Evaluation::Failed => {
+                statistics.failed_evaluations += 1;
+                None
+            }
+            Evaluation::TimedOut(individual) => {
+                statistics.timed_out_evaluations += 1;
+                individual
+            }
+        }
+    }
+}
+
+fn place_all<A: Archive>(
+    archive: &mut A,
+    individuals: Vec<Individual>,
+    statistics: &mut BatchStatistics,
+) {
+    for individual in individuals {
+        try_place(archive, individual, statistics);
+    }
+}
+
+/// Records the insertion, individuals the archive can not place count as failed evaluations.
+fn try_place<A: Archive>(
+    archive: &mut A,
+    individual: Individual,
+    statistics: &mut BatchStatistics,
+) -> Option<Insertion> {
+    match archive.try_place_individual(individual) {
+        Ok(insertion) => {
+            statistics.record(&insertion);
+            Some(insertion)
         }
+        Err(error) => {
+            warn!("could not place evaluated individual: {}", error);
+            statistics.failed_evaluations += 1;
+            None
+        }
+    }
+}
+
+fn call_fitness_function(
+    fitness_function: &FallibleFitnessFunction,
+    individual: &Individual,
+    catch_panics: bool,
+) -> Result<(f64, Vec<f64>), Box<dyn std::error::Error + Send + Sync>> {
+    if catch_panics {
+        panic::catch_unwind(AssertUnwindSafe(|| fitness_function(individual)))
+            .unwrap_or_else(|payload| Err(panic_message(payload).into()))
+    } else {
+        fitness_function(individual)
+    }
+}
+
+/// The message `panic!` was called with, if any.
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => (*message).to_owned(),
+            Err(_) => "evaluation panicked".to_owned(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use super::{Evaluation, Runtime, RuntimeIterator, WarmStart};
+    use crate::statistics::BatchStatistics;
+    use crate::{
+        checkpoint::Checkpoint,
+        elites_map::{CellCapacity, ElitesMap, OutOfRange, Replacement},
+        individual::Individual,
+        parameters::{EvaluationTimeout, FeatureRange, Parameters, Tessellation},
+    };
+
+    /// A single feature within `[0, 1]`, 20 initial runs and batches of 10.
+    fn small_parameters() -> Parameters {
+        let mut parameters = Parameters::default();
+        parameters.map_elites.map_resolution = 10;
+        parameters.map_elites.feature_ranges = vec![FeatureRange::from((0.0, 1.0))];
+        parameters.map_elites.initial_runs = 20;
+        parameters.map_elites.batch_size = 10;
+        parameters
+    }
+
+    /// Evaluates to fitness and behavior drawn from the seed of the individual, i.e. reproducibly.
+    fn runtime(parameters: Parameters) -> Runtime {
+        Runtime::with_parameters(
+            parameters,
+            Box::new(|individual| {
+                let mut rng = SmallRng::seed_from_u64(individual.seed);
+                Ok((rng.gen(), vec![rng.gen()]))
+            }),
+        )
+    }
+
+    #[test]
+    fn drop_failed_evaluations() {
+        let calls = AtomicUsize::new(0);
+
+        let mut rng = SmallRng::seed_from_u64(42);
+
+        let runtime = Runtime::with_parameters(
+            Parameters::default(),
+            Box::new(move |_| {
+                if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    Err("first evaluation fails".into())
+                } else {
+                    Ok((1.0, vec![0.0]))
+                }
+            }),
+        );
+
+        let evaluated = runtime.evaluate_parallel(vec![Individual::default(); 3], &mut rng);
+
+        assert_eq!(evaluated.len(), 3);
+        assert_eq!(
+            evaluated
+                .into_iter()
+                .filter_map(|evaluation| evaluation.record(&mut BatchStatistics::default()))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn catch_panicking_evaluations() {
+        let mut parameters = Parameters::default();
+        parameters.map_elites.catch_panics = true;
+
+        let mut rng = SmallRng::seed_from_u64(42);
+
+        let runtime = Runtime::with_parameters(
+            parameters,
+            Box::new(|individual| {
+                if individual.fitness > 0.0 {
+                    panic!("evaluation panics");
+                }
+                Ok((1.0, vec![0.0]))
+            }),
+        );
+
+        let evaluated = runtime.evaluate_parallel(
+            vec![
+                Individual::default(),
+                Individual {
+                    fitness: 1.0,
+                    ..Default::default()
+                },
+            ],
+            &mut rng,
+        );
+
+        assert!(matches!(evaluated[0], Evaluation::Evaluated(_)));
+        assert!(matches!(evaluated[1], Evaluation::Failed));
+    }
+
+    #[test]
+    fn time_out_evaluations() {
+        let mut parameters = Parameters::default();
+        parameters.map_elites.evaluation_timeout = Some(EvaluationTimeout {
+            milliseconds: 50,
+            penalty_fitness: Some(-1.0),
+        });
+
+        let mut rng = SmallRng::seed_from_u64(42);
+
+        let runtime = Runtime::with_parameters(
+            parameters,
+            Box::new(|individual| {
+                if individual.fitness > 0.0 {
+                    std::thread::sleep(std::time::Duration::from_secs(1));
+                }
+                Ok((1.0, vec![0.0]))
+            }),
+        );
+
+        let evaluated = runtime.evaluate_parallel(
+            vec![
+                Individual::default(),
+                Individual {
+                    fitness: 1.0,
+                    behavior: vec![0.5],
+                    ..Default::default()
+                },
+                Individual {
+                    fitness: 1.0,
+                    ..Default::default()
+                },
+            ],
+            &mut rng,
+        );
+
+        assert!(matches!(evaluated[0], Evaluation::Evaluated(_)));
+        match &evaluated[1] {
+            Evaluation::TimedOut(Some(individual)) => {
+                assert!((individual.fitness + 1.0).abs() < f64::EPSILON);
+                assert_eq!(individual.behavior, vec![0.5]);
+            }
+            _ => panic!("evaluation did not time out"),
+        }
+        // without an inherited behavior the penalized individual has no cell to go to
+        assert!(matches!(evaluated[2], Evaluation::TimedOut(None)));
+    }
+
+    #[test]
+    fn store_penalized_offspring_only_by_fitness() {
+        // a free place and a full cell that replaces at random
+        for &size in &[2, 1] {
+            let mut parameters = small_parameters();
+            parameters.map_elites.initial_runs = 1;
+            parameters.map_elites.cell_capacity = CellCapacity {
+                size,
+                replacement: Replacement::Random,
+            };
+            parameters.map_elites.evaluation_timeout = Some(EvaluationTimeout {
+                milliseconds: 20,
+                penalty_fitness: Some(0.0),
+            });
+
+            // offspring time out and inherit the behavior of their parent
+            let runtime = Runtime::with_parameters(
+                parameters,
+                Box::new(|individual| {
+                    if individual.parent.is_some() {
+                        std::thread::sleep(std::time::Duration::from_millis(200));
+                    }
+                    let mut rng = SmallRng::seed_from_u64(individual.seed);
+                    Ok((1.0, vec![rng.gen()]))
+                }),
+            );
+
+            let mut search = runtime.initilize();
+            let stored = search.archive().individuals().count();
+            let report = search.next().unwrap();
+
+            assert_eq!(report.statistics.timed_out_evaluations, 10);
+            assert_eq!(report.statistics.discarded, 10);
+            assert_eq!(search.archive().individuals().count(), stored);
+        }
+    }
+
+    #[test]
+    fn reproduce_seeded_runs() {
+        let mut parameters = small_parameters();
+        parameters.map_elites.seed = Some(7);
+
+        let runtime = runtime(parameters);
+
+        let run = |threads| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| {
+                    let mut search = runtime.initilize();
+                    search.nth(5);
+                    format!("{:?}", search.archive().individuals().collect::<Vec<_>>())
+                })
+        };
+
+        assert_eq!(run(1), run(4));
+    }
+
+    #[test]
+    fn resume_from_checkpoint() {
+        let path = std::env::temp_dir().join("map_elites_resume_from_checkpoint.json");
+        let path = path.to_str().unwrap();
+
+        let mut parameters = small_parameters();
+        parameters.map_elites.checkpoint = Some(Checkpoint {
+            path: path.to_owned(),
+            frequency: 3,
+        });
+
+        let runtime = runtime(parameters);
+
+        let mut search = runtime.initilize();
+        // the third batch writes the checkpoint
+        search.nth(4);
+
+        let mut resumed = runtime.resume(path).unwrap();
+        assert_eq!(resumed.batches(), 3);
+        resumed.nth(1);
+
+        std::fs::remove_file(path).unwrap();
+
+        let by_id = |archive: &ElitesMap| {
+            let mut individuals: Vec<&Individual> = archive.individuals().collect();
+            individuals.sort_by_key(|individual| individual.id);
+            format!("{:?}", individuals)
+        };
+
+        assert_eq!(by_id(search.archive()), by_id(resumed.archive()));
+
+        // the parents of the next batch are drawn from the restored random state
+        let parent_ids = |search: &mut RuntimeIterator| {
+            search
+                .archive
+                .get_random_individuals(10, &mut search.genome_context.rng)
+                .iter()
+                .map(|individual| individual.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(parent_ids(&mut search), parent_ids(&mut resumed));
+    }
+
+    #[test]
+    fn warm_start() {
+        let mut parameters = Parameters::default();
+        parameters.map_elites.map_resolution = 10;
+        parameters.map_elites.feature_ranges = vec![FeatureRange::from((0.0, 10.0))];
+        parameters.map_elites.initial_runs = 4;
+
+        // seeds keep their fitness and get placed by it, random individuals all end up in the first cell
+        let runtime = Runtime::with_parameters(
+            parameters.clone(),
+            Box::new(|individual| Ok((individual.fitness, vec![individual.fitness]))),
+        );
+
+        let seeds = vec![
+            Individual {
+                fitness: 5.0,
+                ..Default::default()
+            },
+            Individual {
+                fitness: 7.0,
+                ..Default::default()
+            },
+        ];
+
+        let elites_map = runtime.initilize_with_individuals(seeds).into_archive();
+        assert_eq!(elites_map.len(), 3);
+
+        parameters.map_elites.map_resolution = 2;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted_calls = Arc::clone(&calls);
+
+        let runtime = Runtime::with_parameters(
+            parameters,
+            Box::new(move |individual| {
+                counted_calls.fetch_add(1, Ordering::SeqCst);
+                Ok((individual.fitness, vec![individual.fitness]))
+            }),
+        );
+
+        let kept = runtime
+            .initilize_from_archive(elites_map.clone(), WarmStart::default())
+            .archive;
+        assert_eq!(kept.len(), 3);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let rebinned = runtime
+            .initilize_from_archive(
+                elites_map,
+                WarmStart {
+                    rebin: true,
+                    reevaluate: true,
+                },
+            )
+            .archive;
+        assert_eq!(rebinned.len(), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(rebinned
+            .individuals()
+            .all(|individual| individual.parent.is_none()));
+    }
+
+    #[test]
+    fn keep_gene_ids_unique_on_warm_start() {
+        let mut parameters = small_parameters();
+        parameters.map_elites.initial_runs = 4;
+
+        // genomes with more nodes take over the single occupied cell
+        let runtime = Runtime::with_parameters(
+            parameters,
+            Box::new(|individual| Ok((individual.nodes().count() as f64, vec![0.5]))),
+        );
+
+        let mut genome_context = runtime.genome_context();
+        let mut seed = Individual::from_genome(genome_context.uninitialized_genome());
+        seed.init_with_context(&mut genome_context);
+        while seed.nodes().count() < 3 {
+            seed.mutate_with_context(&mut genome_context);
+        }
+
+        // the seed stems from a context of its own, like one loaded from disk
+        let mut search = runtime.initilize_with_individuals(vec![seed]);
+        for _ in 0..50 {
+            search.next();
+        }
+
+        let mut search =
+            runtime.initilize_from_archive(search.into_archive(), WarmStart::default());
+        for _ in 0..50 {
+            search.next();
+        }
+
+        let elite = search.archive().top_individual();
+        assert!(elite.nodes().count() > 3);
+        assert_eq!(
+            elite.nodes().collect::<HashSet<_>>().len(),
+            elite.nodes().count()
+        );
+    }
+
+    #[test]
+    fn cache_centroids() {
+        let mut parameters = small_parameters();
+        parameters.map_elites.tessellation = Tessellation::CentroidalVoronoi {
+            centroids: 5,
+            samples: 100,
+            iterations: 5,
+        };
+
+        let runtime = runtime(parameters);
+
+        let centroids = runtime.centroids(&[(0.0, 1.0)]);
+        assert_eq!(runtime.centroids(&[(0.0, 1.0)]), centroids);
+        assert_ne!(runtime.centroids(&[(0.0, 2.0)]), centroids);
+
+        let cached = runtime.centroids.lock().unwrap();
+        assert_eq!(cached.as_ref().unwrap().feature_ranges, vec![(0.0, 2.0)]);
+    }
+
+    #[test]
+    fn report_batches() {
+        let runtime = runtime(small_parameters());
+
+        let mut search = runtime.initilize();
+        let report = search.nth(1).unwrap();
+
+        assert_eq!(report.batch, 2);
+        assert_eq!(report.statistics.offspring, 10);
+        assert_eq!(report.occupied_cells, search.archive().len());
+        assert!(
+            (report.best_fitness - search.archive().top_individual().fitness).abs() < f64::EPSILON
+        );
+    }
+
+    #[test]
+    fn stop_on_empty_archive() {
+        let mut parameters = small_parameters();
+        parameters.map_elites.initial_runs = 5;
+        parameters.map_elites.out_of_range = OutOfRange::Reject;
+
+        let runtime = Runtime::with_parameters(parameters, Box::new(|_| Ok((1.0, vec![2.0]))));
+
+        let mut search = runtime.initilize();
+
+        assert_eq!(search.batch_statistics().discarded, 5);
+        assert!(search.next().is_none());
+    }
+
+    #[test]
+    fn count_mismatching_descriptors_as_failed() {
+        let mut parameters = small_parameters();
+        parameters.map_elites.initial_runs = 5;
+        parameters.map_elites.batch_size = 5;
+
+        // initial individuals have no parent, offspring get a descriptor with one value too many
+        let runtime = Runtime::with_parameters(
+            parameters,
+            Box::new(|individual| match individual.parent {
+                None => Ok((1.0, vec![0.5])),
+                Some(_) => Ok((1.0, vec![0.5, 0.5])),
+            }),
+        );
 
-        info!("finished batch");
+        let report = runtime.initilize().next().unwrap();
 
-        Some(self.elites_map.clone())
+        assert_eq!(report.statistics.offspring, 5);
+        assert_eq!(report.statistics.failed_evaluations, 5);
+        assert!(report.statistics.success_rate().abs() < f64::EPSILON);
     }
 }
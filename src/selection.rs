@@ -0,0 +1,299 @@
+use std::borrow::Cow;
+use std::cell::OnceCell;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::elites_map::{cell_elite, ElitesMap};
+use crate::individual::Individual;
+
+/// Strategy to pick the cells that provide parents, within a cell parents are picked uniformly.
+pub trait Selector {
+    /// Indices into `candidates` for the next `count` parents, called once per batch.
+    fn select(&self, candidates: &Candidates, count: usize, rng: &mut impl Rng) -> Vec<usize>;
+}
+
+/// Built-in selection strategies.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Selection {
+    /// every occupied cell is equally likely
+    Uniform,
+    /// proportional to the elite's fitness above the worst elite's fitness
+    FitnessWeighted,
+    /// proportional to the share of neighboring cells whose elites are less fit
+    #[default]
+    DominatedNeighbors,
+    /// fittest elite among `size` uniformly drawn cells
+    Tournament { size: usize },
+    /// proportional to the elite's curiosity score, i.e. how many of its offspring got into the map
+    /// minus how many got discarded, shifted so the least curious elite keeps a weight of one
+    Curiosity,
+}
+
+/// The occupied cells of an archive as seen by a selector.
+pub struct Candidates<'a> {
+    elites_map: &'a ElitesMap,
+    cells: Vec<(Cow<'a, [usize]>, &'a [Individual])>,
+    // computed on first use, so selectors that ignore neighbors do not pay for it
+    unstructured_neighborhoods: OnceCell<Option<Vec<Vec<usize>>>>,
+}
+
+impl<'a> Candidates<'a> {
+    pub(crate) fn new(
+        elites_map: &'a ElitesMap,
+        cells: Vec<(Cow<'a, [usize]>, &'a [Individual])>,
+    ) -> Self {
+        Self {
+            elites_map,
+            cells,
+            unstructured_neighborhoods: OnceCell::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// All individuals stored in the cell.
+    pub fn cell(&self, index: usize) -> &'a [Individual] {
+        self.cells[index].1
+    }
+
+    /// The fittest individual of the cell.
+    pub fn elite(&self, index: usize) -> &'a Individual {
+        cell_elite(self.cells[index].1)
+    }
+
+    /// Curiosity score of the cell's elite.
+    pub fn curiosity(&self, index: usize) -> f64 {
+        self.elites_map
+            .curiosity(self.elite(index))
+            .unwrap_or_default()
+    }
+
+    /// Elites of the occupied neighboring cells.
+    pub fn neighbors(&self, index: usize) -> impl Iterator<Item = &Individual> {
+        let unstructured_neighborhoods = self
+            .unstructured_neighborhoods
+            .get_or_init(|| self.elites_map.unstructured_neighborhoods(&self.cells));
+
+        let neighbors: Box<dyn Iterator<Item = &Individual>> = match unstructured_neighborhoods {
+            Some(neighborhoods) => Box::new(
+                neighborhoods[index]
+                    .iter()
+                    .map(move |&neighbor| self.elite(neighbor)),
+            ),
+            None => self.elites_map.neighbors(&self.cells[index].0),
+        };
+
+        neighbors
+    }
+}
+
+impl Selector for Selection {
+    fn select(&self, candidates: &Candidates, count: usize, rng: &mut impl Rng) -> Vec<usize> {
+        match *self {
+            Selection::Uniform => (0..count)
+                .map(|_| rng.gen_range(0..candidates.len()))
+                .collect(),
+            Selection::FitnessWeighted => {
+                let min_fitness = (0..candidates.len())
+                    .map(|index| candidates.elite(index).fitness)
+                    .fold(f64::INFINITY, f64::min);
+
+                let weights: Vec<f64> = (0..candidates.len())
+                    .map(|index| candidates.elite(index).fitness - min_fitness)
+                    .collect();
+
+                // all elites being equally fit leaves nothing to weight by
+                if weights.iter().all(|&weight| weight == 0.0) {
+                    return Selection::Uniform.select(candidates, count, rng);
+                }
+
+                sample_weighted(&weights, count, rng)
+            }
+            Selection::DominatedNeighbors => {
+                let weights: Vec<f64> = (0..candidates.len())
+                    .map(|index| {
+                        let individual = candidates.elite(index);
+                        let neighbors: Vec<&Individual> = candidates.neighbors(index).collect();
+                        let neighbors_count = neighbors.len() as f64;
+                        let dominated_neighbors_count = neighbors
+                            .iter()
+                            .filter(|neighbor| individual.fitness > neighbor.fitness)
+                            .count() as f64;
+                        // always count oneself in as dominated to produce non-zero weight
+                        (dominated_neighbors_count + 1.0) / (neighbors_count + 1.0)
+                    })
+                    .collect();
+
+                sample_weighted(&weights, count, rng)
+            }
+            Selection::Tournament { size } => {
+                assert!(size > 0, "tournament needs at least one participant");
+
+                (0..count)
+                    .map(|_| {
+                        (0..size)
+                            .map(|_| rng.gen_range(0..candidates.len()))
+                            .max_by(|&a, &b| {
+                                candidates
+                                    .elite(a)
+                                    .fitness
+                                    .partial_cmp(&candidates.elite(b).fitness)
+                                    .expect("could not compare floats")
+                            })
+                            .expect("tournament did not have participants")
+                    })
+                    .collect()
+            }
+            Selection::Curiosity => {
+                let scores: Vec<f64> = (0..candidates.len())
+                    .map(|index| candidates.curiosity(index))
+                    .collect();
+
+                let min_score = scores.iter().cloned().fold(f64::INFINITY, f64::min);
+
+                let weights: Vec<f64> =
+                    scores.iter().map(|score| score - min_score + 1.0).collect();
+
+                sample_weighted(&weights, count, rng)
+            }
+        }
+    }
+}
+
+fn sample_weighted(weights: &[f64], count: usize, rng: &mut impl Rng) -> Vec<usize> {
+    let dist = WeightedIndex::new(weights).unwrap();
+
+    (0..count).map(|_| dist.sample(rng)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    use super::{Candidates, Selection, Selector};
+    use crate::elites_map::ElitesMap;
+    use crate::individual::Individual;
+
+    fn elites_map(fitnesses: &[f64]) -> ElitesMap {
+        let mut elites_map = ElitesMap::new(fitnesses.len(), vec![(0.0, fitnesses.len() as f64)]);
+
+        for (index, &fitness) in fitnesses.iter().enumerate() {
+            elites_map.place_individual(Individual {
+                behavior: vec![index as f64 + 0.5],
+                fitness,
+                ..Default::default()
+            });
+        }
+
+        elites_map
+    }
+
+    #[test]
+    fn select_with_builtins() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        let elites_map = elites_map(&[0.0, 1.0, 2.0, 3.0]);
+
+        let parents =
+            elites_map.get_random_individuals_with(&Selection::FitnessWeighted, 100, &mut rng);
+        assert!(parents.iter().all(|parent| parent.fitness > 0.0));
+
+        let parents = elites_map.get_random_individuals_with(
+            &Selection::Tournament { size: 100 },
+            10,
+            &mut rng,
+        );
+        assert!(parents
+            .iter()
+            .all(|parent| (parent.fitness - 3.0).abs() < f64::EPSILON));
+
+        let parents = elites_map.get_random_individuals_with(&Selection::Uniform, 100, &mut rng);
+        assert!(parents
+            .iter()
+            .any(|parent| parent.fitness.abs() < f64::EPSILON));
+
+        let parents =
+            elites_map.get_random_individuals_with(&Selection::DominatedNeighbors, 100, &mut rng);
+        assert_eq!(parents.len(), 100);
+
+        let parents = elites_map.get_random_individuals_with(&Selection::Curiosity, 100, &mut rng);
+        assert_eq!(parents.len(), 100);
+    }
+
+    #[test]
+    fn select_with_custom_selector() {
+        struct First;
+
+        impl Selector for First {
+            fn select(&self, _: &Candidates, count: usize, _: &mut impl Rng) -> Vec<usize> {
+                vec![0; count]
+            }
+        }
+
+        let mut rng = SmallRng::seed_from_u64(42);
+        let elites_map = elites_map(&[4.2]);
+
+        let parents = elites_map.get_random_individuals_with(&First, 3, &mut rng);
+        assert!(parents
+            .iter()
+            .all(|parent| (parent.fitness - 4.2).abs() < f64::EPSILON));
+    }
+
+    #[test]
+    fn find_unstructured_neighbors() {
+        struct CheckNeighbors;
+
+        impl Selector for CheckNeighbors {
+            fn select(
+                &self,
+                candidates: &Candidates,
+                count: usize,
+                _: &mut impl Rng,
+            ) -> Vec<usize> {
+                for index in 0..candidates.len() {
+                    let neighbors: Vec<f64> = candidates
+                        .neighbors(index)
+                        .map(|neighbor| neighbor.behavior[0])
+                        .collect();
+
+                    let expected = match candidates.elite(index).behavior[0] as usize {
+                        0 => vec![1.0, 3.0],
+                        1 => vec![0.0, 3.0],
+                        3 => vec![1.0, 0.0],
+                        7 => vec![3.0, 1.0],
+                        15 => vec![7.0, 3.0],
+                        _ => unreachable!(),
+                    };
+                    assert_eq!(neighbors, expected);
+                }
+
+                vec![0; count]
+            }
+        }
+
+        let mut rng = SmallRng::seed_from_u64(42);
+        let mut elites_map = ElitesMap::unstructured(0.5);
+
+        for &behavior in &[7.0, 0.0, 15.0, 3.0, 1.0] {
+            elites_map.place_individual(Individual {
+                behavior: vec![behavior],
+                ..Default::default()
+            });
+        }
+
+        assert_eq!(
+            elites_map
+                .get_random_individuals_with(&CheckNeighbors, 1, &mut rng)
+                .len(),
+            1
+        );
+    }
+}
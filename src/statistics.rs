@@ -1,6 +1,6 @@
 use serde::Serialize;
 
-use crate::individual::Individual;
+use crate::{archive::Insertion, individual::Individual};
 
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct Statistics {
@@ -45,3 +45,67 @@ pub struct PopulationStatistics {
     pub fitness: FitnessStatisitcs,
     pub novelty: NoveltyStatisitcs,
 }
+
+/// Summary of a finished batch, cheap to produce unlike a copy of the archive.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct BatchReport {
+    /// number of batches so far, including this one
+    pub batch: usize,
+    /// occupied cells of the archive after the batch, cells can hold several individuals
+    pub occupied_cells: usize,
+    /// fitness of the fittest stored individual, negative infinity for an empty archive
+    pub best_fitness: f64,
+    pub statistics: BatchStatistics,
+}
+
+/// Aggregated insertion outcomes of one batch of offspring.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct BatchStatistics {
+    /// evaluated individuals, including those that failed, timed out or were discarded
+    pub offspring: usize,
+    pub new_cells: usize,
+    pub joined: usize,
+    pub replaced: usize,
+    pub discarded: usize,
+    /// sum of fitness deltas over all replacements
+    pub fitness_delta: f64,
+    /// offspring with two parents
+    pub crossovers: usize,
+    /// offspring dropped because their fitness function failed
+    pub failed_evaluations: usize,
+    /// offspring whose evaluation exceeded `evaluation_timeout`
+    pub timed_out_evaluations: usize,
+    /// offspring and successful offspring per configured emitter
+    pub emitters: Vec<EmitterStatistics>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct EmitterStatistics {
+    /// emitted individuals, including those that failed, timed out or were discarded
+    pub offspring: usize,
+    /// emitted individuals that got into the archive
+    pub successes: usize,
+}
+
+impl BatchStatistics {
+    /// Counts the outcome of a placement, the evaluation was counted as offspring before.
+    pub fn record(&mut self, insertion: &Insertion) {
+        match insertion {
+            Insertion::NewCell { .. } => self.new_cells += 1,
+            Insertion::Joined { .. } => self.joined += 1,
+            Insertion::Replaced { fitness_delta, .. } => {
+                self.replaced += 1;
+                self.fitness_delta += fitness_delta;
+            }
+            Insertion::Discarded { .. } => self.discarded += 1,
+        }
+    }
+
+    /// Share of offspring that got into the archive.
+    pub fn success_rate(&self) -> f64 {
+        if self.offspring == 0 {
+            return 0.0;
+        }
+        (self.new_cells + self.joined + self.replaced) as f64 / self.offspring as f64
+    }
+}
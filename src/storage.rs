@@ -0,0 +1,269 @@
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+
+use serde::{Deserialize, Serialize};
+
+use crate::individual::Individual;
+
+/// How an archive keeps its cells in memory.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// hash map keyed by cell position, only occupied cells take up memory
+    #[default]
+    Sparse,
+    /// one slot per cell indexed by the flattened position, needs a bounded number of cells
+    Dense,
+}
+
+/// Occupied cells with their position, dense storage computes positions on the fly.
+pub type StoredCells<'a> = Box<dyn Iterator<Item = (Cow<'a, [usize]>, &'a [Individual])> + 'a>;
+
+/// Hashes with fixed keys, so seeded runs visit cells in the same order.
+pub type CellMap = HashMap<Vec<usize>, Vec<Individual>, BuildHasherDefault<DefaultHasher>>;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum Storage {
+    Sparse(#[serde(with = "sparse_cells")] CellMap),
+    Dense(DenseCells),
+}
+
+/// Cells laid out with the first feature varying fastest.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DenseCells {
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+    slots: Vec<Vec<Individual>>,
+    // one bit per slot, set for every occupied cell
+    occupancy: Vec<u64>,
+    occupied: usize,
+}
+
+impl Storage {
+    /// `shape` holds the number of chunks per dimension and is required by dense storage.
+    pub fn new(backend: StorageBackend, shape: Option<&[usize]>) -> Self {
+        match backend {
+            StorageBackend::Sparse => Storage::Sparse(CellMap::default()),
+            StorageBackend::Dense => Storage::Dense(DenseCells::new(
+                shape.expect("dense storage needs a bounded number of cells"),
+            )),
+        }
+    }
+
+    pub fn backend(&self) -> StorageBackend {
+        match self {
+            Storage::Sparse(_) => StorageBackend::Sparse,
+            Storage::Dense(_) => StorageBackend::Dense,
+        }
+    }
+
+    /// Individuals of an occupied cell.
+    pub fn get(&self, position: &[usize]) -> Option<&[Individual]> {
+        match self {
+            Storage::Sparse(map) => map.get(position).map(Vec::as_slice),
+            Storage::Dense(dense) => dense.get(dense.flatten(position)),
+        }
+    }
+
+    /// The cell counts as occupied from here on and must not be left empty.
+    pub fn cell_mut(&mut self, position: Vec<usize>) -> &mut Vec<Individual> {
+        match self {
+            Storage::Sparse(map) => map.entry(position).or_default(),
+            Storage::Dense(dense) => {
+                let index = dense.flatten(&position);
+                dense.cell_mut(index)
+            }
+        }
+    }
+
+    /// Number of occupied cells.
+    pub fn len(&self) -> usize {
+        match self {
+            Storage::Sparse(map) => map.len(),
+            Storage::Dense(dense) => dense.occupied,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn cells(&self) -> StoredCells<'_> {
+        match self {
+            Storage::Sparse(map) => Box::new(
+                map.iter()
+                    .map(|(position, cell)| (Cow::Borrowed(position.as_slice()), cell.as_slice())),
+            ),
+            Storage::Dense(dense) => Box::new(dense.occupied_indices().map(move |index| {
+                (
+                    Cow::Owned(dense.unflatten(index)),
+                    dense.slots[index].as_slice(),
+                )
+            })),
+        }
+    }
+
+    pub fn individuals(&self) -> Box<dyn Iterator<Item = &Individual> + '_> {
+        match self {
+            Storage::Sparse(map) => Box::new(map.values().flatten()),
+            Storage::Dense(dense) => Box::new(
+                dense
+                    .occupied_indices()
+                    .flat_map(move |index| dense.slots[index].iter()),
+            ),
+        }
+    }
+
+    pub fn into_individuals(self) -> Vec<Individual> {
+        match self {
+            Storage::Sparse(map) => map.into_values().flatten().collect(),
+            Storage::Dense(dense) => dense.slots.into_iter().flatten().collect(),
+        }
+    }
+}
+
+impl DenseCells {
+    fn new(shape: &[usize]) -> Self {
+        let mut strides = Vec::with_capacity(shape.len());
+        let mut size: usize = 1;
+
+        for &chunks in shape {
+            strides.push(size);
+            size = size
+                .checked_mul(chunks)
+                .expect("number of cells exceeds the addressable memory");
+        }
+
+        Self {
+            shape: shape.to_vec(),
+            strides,
+            slots: vec![Vec::new(); size],
+            occupancy: vec![0; size.div_ceil(64)],
+            occupied: 0,
+        }
+    }
+
+    pub fn flatten(&self, position: &[usize]) -> usize {
+        assert!(
+            position.len() == self.shape.len()
+                && position
+                    .iter()
+                    .zip(self.shape.iter())
+                    .all(|(&chunk, &chunks)| chunk < chunks),
+            "position {:?} is outside of the cells",
+            position
+        );
+
+        position
+            .iter()
+            .zip(self.strides.iter())
+            .map(|(chunk, stride)| chunk * stride)
+            .sum()
+    }
+
+    fn unflatten(&self, index: usize) -> Vec<usize> {
+        self.shape
+            .iter()
+            .zip(self.strides.iter())
+            .map(|(&chunks, &stride)| index / stride % chunks)
+            .collect()
+    }
+
+    fn is_occupied(&self, index: usize) -> bool {
+        self.occupancy[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    fn get(&self, index: usize) -> Option<&[Individual]> {
+        if self.is_occupied(index) {
+            Some(&self.slots[index])
+        } else {
+            None
+        }
+    }
+
+    fn cell_mut(&mut self, index: usize) -> &mut Vec<Individual> {
+        if !self.is_occupied(index) {
+            self.occupancy[index / 64] |= 1 << (index % 64);
+            self.occupied += 1;
+        }
+        &mut self.slots[index]
+    }
+
+    fn occupied_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.occupancy
+            .iter()
+            .enumerate()
+            .flat_map(|(word_index, &word)| {
+                let mut word = word;
+                std::iter::from_fn(move || {
+                    if word == 0 {
+                        return None;
+                    }
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    Some(word_index * 64 + bit)
+                })
+            })
+    }
+
+    /// Occupied cells directly adjacent along one dimension, without allocating positions.
+    pub fn neighbors<'a>(
+        &'a self,
+        position: &'a [usize],
+    ) -> impl Iterator<Item = &'a [Individual]> {
+        let index = self.flatten(position);
+
+        position
+            .iter()
+            .zip(self.shape.iter().zip(self.strides.iter()))
+            .flat_map(move |(&chunk, (&chunks, &stride))| {
+                let up = if chunk + 1 < chunks {
+                    Some(index + stride)
+                } else {
+                    None
+                };
+                let down = if chunk > 0 {
+                    Some(index - stride)
+                } else {
+                    None
+                };
+                up.into_iter().chain(down)
+            })
+            .flat_map(move |neighbor| self.get(neighbor))
+    }
+}
+
+/// JSON only has string keys, so sparse cells are stored as a list of positions with their individuals.
+mod sparse_cells {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::CellMap;
+    use crate::individual::Individual;
+
+    pub fn serialize<S: Serializer>(cells: &CellMap, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(cells.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<CellMap, D::Error> {
+        let cells: Vec<(Vec<usize>, Vec<Individual>)> = Vec::deserialize(deserializer)?;
+        Ok(cells.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DenseCells;
+
+    #[test]
+    fn flatten_positions() {
+        let dense = DenseCells::new(&[3, 4, 2]);
+
+        for index in 0..24 {
+            assert_eq!(dense.flatten(&dense.unflatten(index)), index);
+        }
+
+        assert_eq!(dense.flatten(&[2, 1, 1]), 2 + 3 + 12);
+    }
+}